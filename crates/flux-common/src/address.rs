@@ -0,0 +1,44 @@
+use std::{
+    fmt,
+    net::SocketAddr,
+};
+
+/// Address of the remote side of the connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PeerAddress {
+    Tcp(SocketAddr),
+
+    /// Unix domain socket peer, identified by its
+    /// credentials (`SO_PEERCRED`)
+    Unix {
+        pid: Option<i32>,
+        uid: u32,
+        gid: u32,
+    },
+
+    Memory {
+        id: u64,
+    },
+}
+
+impl fmt::Display for PeerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(address) => write!(f, "{address}"),
+            Self::Unix { pid, uid, gid } => {
+                f.write_str("unix(")?;
+                if let Some(pid) = pid {
+                    write!(f, "pid={pid}, ")?;
+                }
+                write!(f, "uid={uid}, gid={gid})")
+            }
+            Self::Memory { id } => write!(f, "memory#{id}"),
+        }
+    }
+}
+
+impl From<SocketAddr> for PeerAddress {
+    fn from(value: SocketAddr) -> Self {
+        Self::Tcp(value)
+    }
+}
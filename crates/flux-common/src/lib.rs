@@ -1,7 +1,13 @@
+use std::fmt;
+
 use bitflags::bitflags;
 
+pub mod address;
+
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "serde", serde(transparent))]
     pub struct Rights: u16 {
         const CAN_PICK_TCP_PORT     = 1 << 0;
         const CAN_PICK_HTTP_DOMAIN  = 1 << 1;
@@ -10,3 +16,10 @@ bitflags! {
         const CAN_CREATE_HTTP_PROXY = 1 << 3;
     }
 }
+
+/// Same `A | B` form as the serialized one
+impl fmt::Display for Rights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        bitflags::parser::to_writer(self, f)
+    }
+}
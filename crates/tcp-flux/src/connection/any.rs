@@ -1,18 +1,30 @@
-use std::net::SocketAddr;
+use std::num::NonZeroU32;
 
-use tokio::net::TcpStream;
+use crate::transport::PeerAddress;
+
+/// Secret of the master session, sent along with its
+/// pending connections. Flow must present it to claim one
+/// of them
+pub type ClaimSecret = [u8; 16];
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConnectionType {
-    Flow { id: u16 },
+    /// Claims pending connection `id` of the proxy bound
+    /// on the `port`, or the oldest one if `id` is not
+    /// specified
+    Flow {
+        port: u16,
+        secret: ClaimSecret,
+        id: Option<NonZeroU32>,
+    },
     Master,
 }
 
 #[derive(Debug)]
-pub struct AnyConnection {
+pub struct AnyConnection<S> {
     pub type_: ConnectionType,
-    pub address: SocketAddr,
-    pub socket: TcpStream,
+    pub address: PeerAddress,
+    pub socket: S,
 }
 
 #[rustfmt::skip]
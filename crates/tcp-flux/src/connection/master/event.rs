@@ -0,0 +1,96 @@
+use flux_common::Rights;
+
+use super::{
+    payloads::{
+        connected::ConnectedPayload,
+        expired::ExpiredPayload,
+        info::InfoPayload,
+        quota::QuotaPayload,
+        tcp_created::TcpCreatedPayload,
+        usage::UsagePayload,
+    },
+    reader::{
+        client::MasterClientReader,
+        common::{
+            Client,
+            MasterReader,
+        },
+    },
+};
+use crate::{
+    connection::traits::RawRead,
+    error::EventReadError,
+    types::{
+        error_code::ErrorCode,
+        pkt_base::PktType,
+    },
+};
+
+/// Packet sent by the server, along with its payload
+#[derive(Debug)]
+pub enum ServerEvent {
+    /// Reply to the information request
+    Info(InfoPayload<'static>),
+
+    /// New connection to the proxy, flow should be opened
+    /// to serve it
+    Connected(ConnectedPayload),
+
+    /// Pending connection was closed since it was not
+    /// claimed in time
+    Expired(ExpiredPayload),
+
+    /// Reply to the TCP proxy creation
+    TcpCreated(TcpCreatedPayload),
+
+    /// Rights of the user were changed, either on the
+    /// authentication or later by the server
+    RightsUpdated(Rights),
+
+    /// Reply to the usage request
+    Usage(UsagePayload),
+
+    /// Reply to the quota request, [`None`] if the user
+    /// has no quota
+    Quota(Option<QuotaPayload>),
+
+    Error(ErrorCode),
+}
+
+impl<R: RawRead> MasterReader<R, Client> {
+    /// Reads the next packet from the server and decodes
+    /// its payload
+    pub async fn next_event(&mut self) -> Result<ServerEvent, EventReadError> {
+        let (pkt, mut reader): (_, MasterClientReader<'_, R>) =
+            self.next_packet().await?;
+
+        let event = match pkt.type_ {
+            PktType::ReqInfo => ServerEvent::Info(reader.read_info().await?),
+            PktType::Connected => {
+                ServerEvent::Connected(reader.read_connected(pkt.flags).await?)
+            }
+            PktType::Expired => ServerEvent::Expired(reader.read_expired().await?),
+            PktType::CreateTcp => {
+                ServerEvent::TcpCreated(reader.read_tcp_created(pkt.flags).await?)
+            }
+            PktType::UpdateRights => {
+                ServerEvent::RightsUpdated(reader.read_update_rights().await?)
+            }
+            PktType::Usage => {
+                ServerEvent::Usage(reader.read_usage(pkt.flags).await?)
+            }
+            PktType::Quota => {
+                ServerEvent::Quota(reader.read_quota(pkt.flags).await?)
+            }
+            PktType::Error => ServerEvent::Error(reader.read_error().await?),
+
+            type_ @ (PktType::Disconnect
+            | PktType::Authenticate
+            | PktType::CreateHttp) => {
+                return Err(EventReadError::UnexpectedPacket(type_));
+            }
+        };
+
+        Ok(event)
+    }
+}
@@ -1,3 +1,4 @@
+pub mod event;
 pub mod reader;
 pub mod writer;
 
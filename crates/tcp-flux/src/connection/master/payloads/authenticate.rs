@@ -0,0 +1,7 @@
+/// Credentials of the user. Both fields are limited to the
+/// 255 bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthenticateRequest {
+    pub username: String,
+    pub password: String,
+}
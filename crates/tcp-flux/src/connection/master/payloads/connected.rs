@@ -0,0 +1,25 @@
+use crate::{
+    connection::any::ClaimSecret,
+    proxy_protocol::ProxiedAddresses,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectedPayload {
+    /// Port of the proxy the connection was accepted by
+    pub port: u16,
+
+    /// Secret of the session the flow claims connection
+    /// with
+    pub secret: ClaimSecret,
+
+    /// Id the flow claims this connection with, unique
+    /// among the pending connections of the proxy
+    pub id: u32,
+
+    /// Present only if the proxy was created with the
+    /// addresses forwarding. Passed to the backend with
+    /// [`write_header`]
+    ///
+    /// [`write_header`]: crate::proxy_protocol::write_header
+    pub addresses: Option<ProxiedAddresses>,
+}
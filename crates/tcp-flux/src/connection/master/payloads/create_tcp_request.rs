@@ -3,4 +3,12 @@ use std::num::NonZeroU16;
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct CreateTcpRequest {
     pub specific_port: Option<NonZeroU16>,
+
+    /// Take random port if `specific_port` can't be
+    /// taken
+    pub fallback_to_random: bool,
+
+    /// Include addresses of the connection in the
+    /// `Connected` packets
+    pub forward_addresses: bool,
 }
@@ -0,0 +1,10 @@
+/// Pending connection was not claimed in time and was
+/// closed by the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpiredPayload {
+    /// Port of the proxy the connection was accepted by
+    pub port: u16,
+
+    /// Id the connection was announced with
+    pub id: u32,
+}
@@ -1,2 +1,8 @@
+pub mod authenticate;
+pub mod connected;
 pub mod create_tcp_request;
+pub mod expired;
 pub mod info;
+pub mod quota;
+pub mod tcp_created;
+pub mod usage;
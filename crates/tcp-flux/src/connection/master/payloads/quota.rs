@@ -0,0 +1,21 @@
+/// Data quota of the authenticated user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaPayload {
+    /// Bytes forwarded in both directions during the
+    /// current period
+    pub used: u64,
+
+    /// Bytes allowed per period
+    pub limit: u64,
+
+    /// Seconds since the epoch when the period ends and
+    /// `used` is reset
+    pub resets_at: u64,
+}
+
+impl QuotaPayload {
+    /// Bytes left until the quota is exceeded
+    pub const fn remaining(&self) -> u64 {
+        self.limit.saturating_sub(self.used)
+    }
+}
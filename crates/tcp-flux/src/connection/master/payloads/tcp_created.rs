@@ -0,0 +1,8 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TcpCreatedPayload {
+    pub port: u16,
+
+    /// Requested port couldn't be taken, so the random one
+    /// was granted instead
+    pub fell_back: bool,
+}
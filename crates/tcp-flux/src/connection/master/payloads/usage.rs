@@ -0,0 +1,21 @@
+/// Traffic of the proxied connections
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TrafficCounters {
+    pub connections: u64,
+
+    /// Bytes received from the public peers
+    pub bytes_received: u64,
+
+    /// Bytes sent to the public peers
+    pub bytes_sent: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsagePayload {
+    /// Totals of the authenticated user since the server
+    /// was started
+    pub user: Option<TrafficCounters>,
+
+    /// Totals of the proxies held by the session
+    pub proxy: Option<TrafficCounters>,
+}
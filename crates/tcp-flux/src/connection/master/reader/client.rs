@@ -1,13 +1,31 @@
 use std::borrow::Cow;
 
+use flux_common::Rights;
 use tokio::io::ReadBuf;
 
 use crate::{
     connection::{
-        master::payloads::info::InfoPayload,
+        any::ClaimSecret,
+        master::payloads::{
+            connected::ConnectedPayload,
+            expired::ExpiredPayload,
+            info::InfoPayload,
+            quota::QuotaPayload,
+            tcp_created::TcpCreatedPayload,
+            usage::{
+                TrafficCounters,
+                UsagePayload,
+            },
+        },
         traits::RawRead,
+        utils::read_socket_addr,
     },
     error::ReadError,
+    proxy_protocol::ProxiedAddresses,
+    types::{
+        error_code::ErrorCode,
+        pkt_base::PktFlags,
+    },
 };
 
 type ReadResult<T> = Result<T, ReadError>;
@@ -22,9 +40,123 @@ impl<'a, R: RawRead> MasterClientReader<'a, R> {
             server_name: Cow::Owned(self.read_string().await?),
         })
     }
+
+    /// Reads payload of the `Connected` packet: port of the
+    /// proxy, claim secret and id of the connection,
+    /// optionally followed by the addresses
+    ///
+    /// ### How flags affect the behavior
+    /// - [`PktFlags::FLAG0`]: if set, addresses of the
+    ///   connection are read
+    pub async fn read_connected(
+        &mut self,
+        flags: PktFlags,
+    ) -> ReadResult<ConnectedPayload> {
+        let port = self.reader.read_u16_le().await?;
+        let mut secret = ClaimSecret::default();
+        self.reader.read_exact(&mut secret).await?;
+        let id = self.reader.read_u32_le().await?;
+        let addresses = if flags.contains(PktFlags::FLAG0) {
+            Some(ProxiedAddresses {
+                source: read_socket_addr(self.reader).await?,
+                destination: read_socket_addr(self.reader).await?,
+            })
+        } else {
+            None
+        };
+
+        Ok(ConnectedPayload {
+            port,
+            secret,
+            id,
+            addresses,
+        })
+    }
+
+    /// Reads code of the error reported by the server
+    pub async fn read_error(&mut self) -> ReadResult<ErrorCode> {
+        let code = self.reader.read_u8().await?;
+        ErrorCode::try_from(code).map_err(|_| ReadError::UnknownErrorCode(code))
+    }
+
+    /// Reads rights of the user. Unknown bits are dropped
+    pub async fn read_update_rights(&mut self) -> ReadResult<Rights> {
+        let bits = self.reader.read_u16_le().await?;
+        Ok(Rights::from_bits_truncate(bits))
+    }
+
+    /// Reads port of the created TCP proxy
+    ///
+    /// ### How flags affect the behavior
+    /// - [`PktFlags::FLAG1`]: if set, requested port was
+    ///   not granted and the random one was taken instead
+    pub async fn read_tcp_created(
+        &mut self,
+        flags: PktFlags,
+    ) -> ReadResult<TcpCreatedPayload> {
+        Ok(TcpCreatedPayload {
+            port: self.reader.read_u16_le().await?,
+            fell_back: flags.contains(PktFlags::FLAG1),
+        })
+    }
+
+    /// Reads traffic counters
+    ///
+    /// ### How flags affect the behavior
+    /// - [`PktFlags::FLAG0`]: if set, counters of the user
+    ///   are read
+    /// - [`PktFlags::FLAG1`]: if set, counters of the proxy
+    ///   are read
+    pub async fn read_usage(&mut self, flags: PktFlags) -> ReadResult<UsagePayload> {
+        let user = if flags.contains(PktFlags::FLAG0) {
+            Some(self.read_traffic().await?)
+        } else {
+            None
+        };
+        let proxy = if flags.contains(PktFlags::FLAG1) {
+            Some(self.read_traffic().await?)
+        } else {
+            None
+        };
+
+        Ok(UsagePayload { user, proxy })
+    }
+
+    /// Reads payload of the `Expired` packet
+    pub async fn read_expired(&mut self) -> ReadResult<ExpiredPayload> {
+        Ok(ExpiredPayload {
+            port: self.reader.read_u16_le().await?,
+            id: self.reader.read_u32_le().await?,
+        })
+    }
+
+    /// Reads data quota of the user, present only if the
+    /// [`PktFlags::FLAG0`] is set
+    pub async fn read_quota(
+        &mut self,
+        flags: PktFlags,
+    ) -> ReadResult<Option<QuotaPayload>> {
+        if !flags.contains(PktFlags::FLAG0) {
+            return Ok(None);
+        }
+
+        Ok(Some(QuotaPayload {
+            used: self.reader.read_u64_le().await?,
+            limit: self.reader.read_u64_le().await?,
+            resets_at: self.reader.read_u64_le().await?,
+        }))
+    }
 }
 
 impl<'a, R: RawRead> MasterClientReader<'a, R> {
+    async fn read_traffic(&mut self) -> ReadResult<TrafficCounters> {
+        Ok(TrafficCounters {
+            connections: self.reader.read_u64_le().await?,
+            bytes_received: self.reader.read_u64_le().await?,
+            bytes_sent: self.reader.read_u64_le().await?,
+        })
+    }
+
     async fn read_string(&mut self) -> ReadResult<String> {
         let str_len = self.reader.read_u8().await?;
         let buf = self.read_buffer(str_len as usize).await?;
@@ -5,7 +5,10 @@ use std::{
 
 use crate::{
     connection::{
-        master::payloads::create_tcp_request::CreateTcpRequest,
+        master::payloads::{
+            authenticate::AuthenticateRequest,
+            create_tcp_request::CreateTcpRequest,
+        },
         traits::RawRead,
     },
     types::pkt_base::PktFlags,
@@ -23,18 +26,50 @@ impl<'a, R: RawRead> MasterServerReader<'a, R> {
     /// ### How flags affect the behavior
     /// - [`PktFlags::FLAG0`]: if set, port will not be
     ///   read, leaving [`None`] value instead
+    /// - [`PktFlags::FLAG1`]: if set, random port is taken
+    ///   when the specified one is unavailable
+    /// - [`PktFlags::FLAG2`]: if set, addresses of the
+    ///   connections will be sent in the `Connected`
+    ///   packets
     pub async fn read_create_tcp_request(
         &mut self,
         flags: PktFlags,
     ) -> io::Result<CreateTcpRequest> {
-        if flags.contains(PktFlags::FLAG0) {
-            Ok(CreateTcpRequest {
-                specific_port: None,
-            })
+        let specific_port = if flags.contains(PktFlags::FLAG0) {
+            None
         } else {
-            Ok(CreateTcpRequest {
-                specific_port: NonZeroU16::new(self.reader.read_u16_le().await?),
-            })
-        }
+            NonZeroU16::new(self.reader.read_u16_le().await?)
+        };
+
+        Ok(CreateTcpRequest {
+            specific_port,
+            fallback_to_random: flags.contains(PktFlags::FLAG1),
+            forward_addresses: flags.contains(PktFlags::FLAG2),
+        })
+    }
+
+    /// Reads credentials of the user
+    pub async fn read_authenticate_request(
+        &mut self,
+    ) -> io::Result<AuthenticateRequest> {
+        Ok(AuthenticateRequest {
+            username: self.read_string().await?,
+            password: self.read_string().await?,
+        })
+    }
+}
+
+impl<'a, R: RawRead> MasterServerReader<'a, R> {
+    async fn read_string(&mut self) -> io::Result<String> {
+        let len = self.reader.read_u8().await?;
+        let mut buf = vec![0; len as usize];
+        self.reader.read_exact(&mut buf).await?;
+
+        String::from_utf8(buf).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "got invalid sequence of UTF-8 characters",
+            )
+        })
     }
 }
@@ -1 +1,104 @@
-pub struct MasterClientWriter {}
+use std::io;
+
+use crate::{
+    connection::{
+        master::payloads::{
+            authenticate::AuthenticateRequest,
+            create_tcp_request::CreateTcpRequest,
+        },
+        traits::RawWrite,
+    },
+    types::pkt_base::{
+        PktBase,
+        PktFlags,
+        PktType,
+    },
+};
+
+pub struct MasterClientWriter<W> {
+    writer: W,
+}
+
+impl<W: RawWrite> MasterClientWriter<W> {
+    pub async fn write_req_info(&mut self) -> io::Result<()> {
+        self.writer
+            .write_u8(PktBase::simple(PktType::ReqInfo).encode())
+            .await
+    }
+
+    /// Requests traffic counters of the user and the proxy
+    pub async fn write_req_usage(&mut self) -> io::Result<()> {
+        self.writer
+            .write_u8(PktBase::simple(PktType::Usage).encode())
+            .await
+    }
+
+    /// Requests data quota of the user
+    pub async fn write_req_quota(&mut self) -> io::Result<()> {
+        self.writer
+            .write_u8(PktBase::simple(PktType::Quota).encode())
+            .await
+    }
+
+    /// Sends credentials of the user. Fails with the
+    /// [`io::ErrorKind::InvalidInput`] if any of them is
+    /// longer than 255 bytes
+    pub async fn write_authenticate(
+        &mut self,
+        request: &AuthenticateRequest,
+    ) -> io::Result<()> {
+        let mut buf =
+            Vec::with_capacity(3 + request.username.len() + request.password.len());
+        buf.push(PktBase::simple(PktType::Authenticate).encode());
+        for field in [&request.username, &request.password] {
+            let len = u8::try_from(field.len()).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "credentials are too long",
+                )
+            })?;
+            buf.push(len);
+            buf.extend_from_slice(field.as_bytes());
+        }
+
+        self.writer.write_all(&buf).await
+    }
+
+    /// Requests creation of the TCP proxy. See
+    /// [`crate::connection::master::reader::server::MasterServerReader::read_create_tcp_request`]
+    /// for the flags meaning
+    pub async fn write_create_tcp(
+        &mut self,
+        request: CreateTcpRequest,
+    ) -> io::Result<()> {
+        let mut flags = PktFlags::empty();
+        flags.set(PktFlags::FLAG1, request.fallback_to_random);
+        flags.set(PktFlags::FLAG2, request.forward_addresses);
+
+        match request.specific_port {
+            Some(port) => {
+                let [lo, hi] = port.get().to_le_bytes();
+                self.writer
+                    .write_all(&[
+                        PktBase::new(PktType::CreateTcp, flags).encode(),
+                        lo,
+                        hi,
+                    ])
+                    .await
+            }
+
+            None => {
+                flags |= PktFlags::FLAG0;
+                self.writer
+                    .write_u8(PktBase::new(PktType::CreateTcp, flags).encode())
+                    .await
+            }
+        }
+    }
+}
+
+impl<W> MasterClientWriter<W> {
+    pub const fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
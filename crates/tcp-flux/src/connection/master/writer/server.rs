@@ -1,14 +1,25 @@
 use std::io;
 
+use flux_common::Rights;
+
 use crate::{
     connection::{
-        master::payloads::info::InfoPayload,
+        master::payloads::{
+            connected::ConnectedPayload,
+            expired::ExpiredPayload,
+            info::InfoPayload,
+            quota::QuotaPayload,
+            tcp_created::TcpCreatedPayload,
+            usage::UsagePayload,
+        },
         traits::RawWrite,
+        utils::encode_socket_addr,
     },
     types::{
         error_code::ErrorCode,
         pkt_base::{
             PktBase,
+            PktFlags,
             PktType,
         },
     },
@@ -19,12 +30,117 @@ pub struct MasterServerWriter<W> {
 }
 
 impl<W: RawWrite> MasterServerWriter<W> {
-    pub async fn write_connected(&mut self) -> io::Result<()> {
+    /// Notifies client about the new connection on the
+    /// proxy bound on the `payload.port`. If addresses
+    /// are present, [`PktFlags::FLAG0`] is set and they
+    /// are appended to the packet.
+    pub async fn write_connected(
+        &mut self,
+        payload: ConnectedPayload,
+    ) -> io::Result<()> {
+        let mut flags = PktFlags::empty();
+        flags.set(PktFlags::FLAG0, payload.addresses.is_some());
+
+        let mut buf = Vec::with_capacity(1 + 2 + 16 + 4 + 2 * 19);
+        buf.push(PktBase::new(PktType::Connected, flags).encode());
+        buf.extend_from_slice(&payload.port.to_le_bytes());
+        buf.extend_from_slice(&payload.secret);
+        buf.extend_from_slice(&payload.id.to_le_bytes());
+        if let Some(addresses) = payload.addresses {
+            encode_socket_addr(&mut buf, addresses.source);
+            encode_socket_addr(&mut buf, addresses.destination);
+        }
+
+        self.writer.write_all(&buf).await
+    }
+
+    /// Notifies client that the pending connection was
+    /// closed since it was not claimed in time
+    pub async fn write_expired(
+        &mut self,
+        payload: ExpiredPayload,
+    ) -> io::Result<()> {
+        let mut buf = Vec::with_capacity(1 + 2 + 4);
+        buf.push(PktBase::simple(PktType::Expired).encode());
+        buf.extend_from_slice(&payload.port.to_le_bytes());
+        buf.extend_from_slice(&payload.id.to_le_bytes());
+
+        self.writer.write_all(&buf).await
+    }
+
+    /// Confirms creation of the TCP proxy. Sets
+    /// [`PktFlags::FLAG1`] if the requested port was not
+    /// granted
+    pub async fn write_tcp_created(
+        &mut self,
+        payload: TcpCreatedPayload,
+    ) -> io::Result<()> {
+        let mut flags = PktFlags::empty();
+        flags.set(PktFlags::FLAG1, payload.fell_back);
+
+        let [lo, hi] = payload.port.to_le_bytes();
+        self.writer
+            .write_all(&[PktBase::new(PktType::CreateTcp, flags).encode(), lo, hi])
+            .await
+    }
+
+    /// Notifies client about the current rights of the user
+    pub async fn write_update_rights(&mut self, rights: Rights) -> io::Result<()> {
+        let [lo, hi] = rights.bits().to_le_bytes();
         self.writer
-            .write_u8(PktBase::simple(PktType::Connected).encode())
+            .write_all(&[PktBase::simple(PktType::UpdateRights).encode(), lo, hi])
             .await
     }
 
+    /// Sends traffic counters. [`PktFlags::FLAG0`] is set
+    /// if the user counters are present and
+    /// [`PktFlags::FLAG1`] if the proxy ones are, they
+    /// follow in that order
+    pub async fn write_usage(&mut self, payload: UsagePayload) -> io::Result<()> {
+        let mut flags = PktFlags::empty();
+        flags.set(PktFlags::FLAG0, payload.user.is_some());
+        flags.set(PktFlags::FLAG1, payload.proxy.is_some());
+
+        let mut buf = Vec::with_capacity(1 + 2 * 24);
+        buf.push(PktBase::new(PktType::Usage, flags).encode());
+        for counters in [payload.user, payload.proxy]
+            .into_iter()
+            .flatten()
+        {
+            for value in [
+                counters.connections,
+                counters.bytes_received,
+                counters.bytes_sent,
+            ] {
+                buf.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        self.writer.write_all(&buf).await
+    }
+
+    /// Sends data quota of the user. [`PktFlags::FLAG0`] is
+    /// set if the user has one
+    pub async fn write_quota(
+        &mut self,
+        payload: Option<QuotaPayload>,
+    ) -> io::Result<()> {
+        let Some(quota) = payload else {
+            return self
+                .writer
+                .write_u8(PktBase::simple(PktType::Quota).encode())
+                .await;
+        };
+
+        let mut buf = Vec::with_capacity(1 + 24);
+        buf.push(PktBase::new(PktType::Quota, PktFlags::FLAG0).encode());
+        for value in [quota.used, quota.limit, quota.resets_at] {
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+
+        self.writer.write_all(&buf).await
+    }
+
     pub async fn write_error(&mut self, error: ErrorCode) -> io::Result<()> {
         self.writer
             .write_all(&[PktBase::simple(PktType::Error).encode(), error as u8])
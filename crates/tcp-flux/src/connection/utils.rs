@@ -0,0 +1,49 @@
+use std::net::{
+    IpAddr,
+    Ipv4Addr,
+    Ipv6Addr,
+    SocketAddr,
+};
+
+use super::traits::RawRead;
+use crate::error::ReadError;
+
+const FAMILY_V4: u8 = 4;
+const FAMILY_V6: u8 = 6;
+
+/// Encodes socket address as the family byte, IP address
+/// and little-endian port
+pub fn encode_socket_addr(buf: &mut Vec<u8>, address: SocketAddr) {
+    match address.ip() {
+        IpAddr::V4(ip) => {
+            buf.push(FAMILY_V4);
+            buf.extend_from_slice(&ip.octets());
+        }
+        IpAddr::V6(ip) => {
+            buf.push(FAMILY_V6);
+            buf.extend_from_slice(&ip.octets());
+        }
+    }
+    buf.extend_from_slice(&address.port().to_le_bytes());
+}
+
+pub async fn read_socket_addr<R: RawRead>(
+    reader: &mut R,
+) -> Result<SocketAddr, ReadError> {
+    let ip = match reader.read_u8().await? {
+        FAMILY_V4 => {
+            let mut octets = [0; 4];
+            reader.read_exact(&mut octets).await?;
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        FAMILY_V6 => {
+            let mut octets = [0; 16];
+            reader.read_exact(&mut octets).await?;
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+
+        family => return Err(ReadError::InvalidAddressFamily(family)),
+    };
+
+    Ok(SocketAddr::new(ip, reader.read_u16_le().await?))
+}
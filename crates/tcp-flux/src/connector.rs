@@ -0,0 +1,46 @@
+use std::{
+    io,
+    num::NonZeroU32,
+};
+
+use tokio::io::AsyncWriteExt;
+
+use crate::{
+    connection::any::ConnectionType,
+    transport::Dialer,
+};
+
+/// Client-side counterpart of the
+/// [`crate::listener::Listener`]: establishes connection
+/// and selects its type
+pub struct Connector<D> {
+    dialer: D,
+}
+
+impl<D: Dialer> Connector<D> {
+    pub async fn connect(&self, type_: ConnectionType) -> io::Result<D::Stream> {
+        let mut socket = self.dialer.dial().await?;
+        match type_ {
+            ConnectionType::Master => {
+                socket
+                    .write_u8(ConnectionType::MASTER_INT)
+                    .await?;
+            }
+
+            ConnectionType::Flow { port, secret, id } => {
+                let mut buf = Vec::with_capacity(1 + 2 + 16 + 4);
+                buf.push(ConnectionType::FLOW_INT);
+                buf.extend_from_slice(&port.to_le_bytes());
+                buf.extend_from_slice(&secret);
+                buf.extend_from_slice(&id.map_or(0, NonZeroU32::get).to_le_bytes());
+                socket.write_all(&buf).await?;
+            }
+        }
+
+        Ok(socket)
+    }
+
+    pub const fn new(dialer: D) -> Self {
+        Self { dialer }
+    }
+}
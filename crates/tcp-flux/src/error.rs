@@ -2,6 +2,8 @@ use std::io;
 
 use thiserror::Error;
 
+use crate::types::pkt_base::PktType;
+
 #[derive(Debug, Error)]
 pub enum ReadError {
     #[error("I/O error: {0}")]
@@ -12,6 +14,12 @@ pub enum ReadError {
 
     #[error("got invalid sequence of UTF-8 characters")]
     InvalidString,
+
+    #[error("invalid address family: {0}")]
+    InvalidAddressFamily(u8),
+
+    #[error("unknown error code: 0x{0:x}")]
+    UnknownErrorCode(u8),
 }
 
 #[derive(Debug, Error)]
@@ -23,6 +31,18 @@ pub enum PktBaseReadError {
     InvalidType(u8),
 }
 
+#[derive(Debug, Error)]
+pub enum EventReadError {
+    #[error(transparent)]
+    Base(#[from] PktBaseReadError),
+
+    #[error(transparent)]
+    Payload(#[from] ReadError),
+
+    #[error("unexpected packet from the server: {0:?}")]
+    UnexpectedPacket(PktType),
+}
+
 #[derive(Debug, Error)]
 pub enum AcceptError {
     #[error("I/O error: {0}")]
@@ -31,3 +51,19 @@ pub enum AcceptError {
     #[error("wrong protocol selected: 0x{0:x}")]
     WrongProtocol(u8),
 }
+
+#[cfg(feature = "tls")]
+#[derive(Debug, Error)]
+pub enum TlsError {
+    #[error("failed to read PEM file: {0}")]
+    Pem(#[from] tokio_rustls::rustls::pki_types::pem::Error),
+
+    #[error("TLS error: {0}")]
+    Rustls(#[from] tokio_rustls::rustls::Error),
+
+    #[error("no certificates found")]
+    NoCertificates,
+
+    #[error("invalid certificate fingerprint")]
+    InvalidFingerprint,
+}
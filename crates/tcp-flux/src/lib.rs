@@ -1,5 +1,11 @@
 pub mod connection;
+pub mod connector;
 pub mod listener;
+pub mod proxy_protocol;
+pub mod transport;
 
 pub mod error;
 pub mod types;
+
+#[cfg(feature = "tls")]
+pub mod tls;
@@ -1,39 +1,80 @@
-use std::io;
-
-use tokio::{
-    io::AsyncReadExt,
-    net::{
-        TcpListener,
-        ToSocketAddrs,
-    },
+use std::{
+    io,
+    num::NonZeroU32,
+    sync::Arc,
 };
 
+use tokio::io::AsyncReadExt;
+
 use crate::{
     connection::any::{
         AnyConnection,
+        ClaimSecret,
         ConnectionType,
     },
     error::AcceptError,
+    transport::{
+        Acceptor,
+        PeerAddress,
+    },
 };
 
-pub struct Listener {
-    handle: TcpListener,
+pub struct Listener<A> {
+    acceptor: Arc<A>,
 }
 
-impl Listener {
-    pub const fn inner_ref(&self) -> &TcpListener {
-        &self.handle
+/// Accepted connection which has not yet performed the
+/// handshake
+pub struct Incoming<A: Acceptor> {
+    pub address: PeerAddress,
+
+    raw: A::Incoming,
+    acceptor: Arc<A>,
+}
+
+impl<A: Acceptor> Listener<A> {
+    pub fn acceptor(&self) -> &A {
+        &self.acceptor
+    }
+
+    /// Accepts next connection. Handshake is left to the
+    /// caller, so it can be performed concurrently with
+    /// accepting other connections.
+    pub async fn next_connection(&self) -> io::Result<Incoming<A>> {
+        let (raw, address) = self.acceptor.accept().await?;
+        Ok(Incoming {
+            address,
+            raw,
+            acceptor: Arc::clone(&self.acceptor),
+        })
+    }
+
+    pub fn new(acceptor: A) -> Self {
+        Self {
+            acceptor: Arc::new(acceptor),
+        }
     }
+}
 
-    pub async fn next_connection(&self) -> Result<AnyConnection, AcceptError> {
-        let (mut socket, address) = self.handle.accept().await?;
-        socket.set_nodelay(true)?;
+impl<A: Acceptor> Incoming<A> {
+    /// Upgrades the transport and reads type of the
+    /// connection
+    pub async fn handshake(self) -> Result<AnyConnection<A::Stream>, AcceptError> {
+        let mut address = self.address;
+        let mut socket = self
+            .acceptor
+            .upgrade(self.raw, &mut address)
+            .await?;
         let prot_int = socket.read_u8().await?;
 
         let conn_type = match prot_int {
             ConnectionType::FLOW_INT => {
-                let flow_id = socket.read_u16_le().await?;
-                ConnectionType::Flow { id: flow_id }
+                let port = socket.read_u16_le().await?;
+                let mut secret = ClaimSecret::default();
+                socket.read_exact(&mut secret).await?;
+                // Zero stands for the oldest pending connection
+                let id = NonZeroU32::new(socket.read_u32_le().await?);
+                ConnectionType::Flow { port, secret, id }
             }
             ConnectionType::MASTER_INT => ConnectionType::Master,
 
@@ -46,9 +87,4 @@ impl Listener {
             socket,
         })
     }
-
-    pub async fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
-        let handle = TcpListener::bind(address).await?;
-        Ok(Self { handle })
-    }
 }
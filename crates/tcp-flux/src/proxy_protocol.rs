@@ -0,0 +1,390 @@
+//! HAProxy PROXY protocol (v1 and v2) headers, used to
+//! pass the original addresses of the proxied connection.
+//!
+//! See <https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt>
+
+use std::{
+    io,
+    net::{
+        IpAddr,
+        Ipv4Addr,
+        Ipv6Addr,
+        SocketAddr,
+    },
+};
+
+use crate::connection::traits::{
+    RawRead,
+    RawWrite,
+};
+
+/// Signature of the binary (v2) header
+pub const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Maximum length of the v1 header, including `\r\n`
+const V1_MAX_LENGTH: usize = 107;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyVersion {
+    /// Human-readable header
+    V1,
+    /// Binary header
+    V2,
+}
+
+/// Addresses of the proxied connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProxiedAddresses {
+    pub source: SocketAddr,
+    pub destination: SocketAddr,
+}
+
+impl ProxiedAddresses {
+    /// Encodes PROXY header of the specified version. Meant
+    /// for the clients, which prepend it to the connections
+    /// to their backends
+    pub fn encode(self, version: ProxyVersion) -> Vec<u8> {
+        match version {
+            ProxyVersion::V1 => self.encode_v1(),
+            ProxyVersion::V2 => self.encode_v2(),
+        }
+    }
+
+    fn encode_v1(self) -> Vec<u8> {
+        let (source, destination) = self.same_family();
+        let family = if source.is_ipv4() { "TCP4" } else { "TCP6" };
+
+        format!(
+            "PROXY {family} {} {} {} {}\r\n",
+            source.ip(),
+            destination.ip(),
+            source.port(),
+            destination.port()
+        )
+        .into_bytes()
+    }
+
+    fn encode_v2(self) -> Vec<u8> {
+        // Version 2, PROXY command
+        const VERSION_COMMAND: u8 = 0x21;
+        const TCP4: u8 = 0x11;
+        const TCP6: u8 = 0x21;
+
+        let (source, destination) = self.same_family();
+        let mut header = Vec::with_capacity(16 + 36);
+        header.extend_from_slice(&V2_SIGNATURE);
+        header.push(VERSION_COMMAND);
+
+        match (source.ip(), destination.ip()) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                header.push(TCP4);
+                header.extend_from_slice(&12_u16.to_be_bytes());
+                header.extend_from_slice(&src.octets());
+                header.extend_from_slice(&dst.octets());
+            }
+
+            (src, dst) => {
+                header.push(TCP6);
+                header.extend_from_slice(&36_u16.to_be_bytes());
+                header.extend_from_slice(&to_ipv6(src).octets());
+                header.extend_from_slice(&to_ipv6(dst).octets());
+            }
+        }
+        header.extend_from_slice(&source.port().to_be_bytes());
+        header.extend_from_slice(&destination.port().to_be_bytes());
+
+        header
+    }
+
+    /// Header can't carry addresses of the different
+    /// families, so mismatching IPv4 address is mapped to
+    /// the IPv6
+    fn same_family(self) -> (SocketAddr, SocketAddr) {
+        let Self {
+            source,
+            destination,
+        } = self;
+        if source.is_ipv4() == destination.is_ipv4() {
+            (source, destination)
+        } else {
+            (
+                SocketAddr::new(to_ipv6(source.ip()).into(), source.port()),
+                SocketAddr::new(
+                    to_ipv6(destination.ip()).into(),
+                    destination.port(),
+                ),
+            )
+        }
+    }
+}
+
+/// Header carrying no addresses, `LOCAL` command for the v2
+fn encode_local(version: ProxyVersion) -> Vec<u8> {
+    match version {
+        ProxyVersion::V1 => b"PROXY UNKNOWN\r\n".to_vec(),
+        ProxyVersion::V2 => {
+            // Version 2, LOCAL command, unspecified family
+            let mut header = V2_SIGNATURE.to_vec();
+            header.extend_from_slice(&[0x20, 0x00, 0x00, 0x00]);
+            header
+        }
+    }
+}
+
+/// Writes PROXY header to the connection to the backend,
+/// before any of the forwarded data. `addresses` come from
+/// the [`ConnectedPayload`], header without them is written
+/// if the proxy does not forward addresses, so the backend
+/// still sees the header it expects.
+///
+/// [`ConnectedPayload`]: crate::connection::master::payloads::connected::ConnectedPayload
+pub async fn write_header<W: RawWrite>(
+    writer: &mut W,
+    addresses: Option<ProxiedAddresses>,
+    version: ProxyVersion,
+) -> io::Result<()> {
+    let header = match addresses {
+        Some(addresses) => addresses.encode(version),
+        None => encode_local(version),
+    };
+    writer.write_all(&header).await
+}
+
+fn to_ipv6(ip: IpAddr) -> std::net::Ipv6Addr {
+    match ip {
+        IpAddr::V4(v4) => v4.to_ipv6_mapped(),
+        IpAddr::V6(v6) => v6,
+    }
+}
+
+/// Reads PROXY header of any version from the `reader`.
+/// Returns [`None`] if the header does not carry addresses
+/// (`LOCAL` command, `UNKNOWN` or non-TCP family), which
+/// means that connection was made by the proxy itself.
+///
+/// Header is read exactly, without touching any data
+/// after it.
+pub async fn read_header<R: RawRead>(
+    reader: &mut R,
+) -> io::Result<Option<ProxiedAddresses>> {
+    let mut signature = [0; V2_SIGNATURE.len()];
+    reader.read_exact(&mut signature[..5]).await?;
+
+    if &signature[..5] == b"PROXY" {
+        return read_v1(reader).await;
+    }
+
+    reader.read_exact(&mut signature[5..]).await?;
+    if signature == V2_SIGNATURE {
+        read_v2(reader).await
+    } else {
+        Err(invalid_header())
+    }
+}
+
+async fn read_v1<R: RawRead>(
+    reader: &mut R,
+) -> io::Result<Option<ProxiedAddresses>> {
+    let mut line = Vec::with_capacity(V1_MAX_LENGTH);
+    line.extend_from_slice(b"PROXY");
+
+    while !line.ends_with(b"\r\n") {
+        if line.len() == V1_MAX_LENGTH {
+            return Err(invalid_header());
+        }
+        line.push(reader.read_u8().await?);
+    }
+
+    let line = std::str::from_utf8(&line[..line.len() - 2])
+        .map_err(|_| invalid_header())?;
+    let mut parts = line.split(' ').skip(1);
+    match parts.next() {
+        Some("TCP4" | "TCP6") => {}
+        Some("UNKNOWN") => return Ok(None),
+        _ => return Err(invalid_header()),
+    }
+
+    let mut next = || parts.next().ok_or_else(invalid_header);
+    let (src, dst, src_port, dst_port) = (next()?, next()?, next()?, next()?);
+    if parts.next().is_some() {
+        return Err(invalid_header());
+    }
+    let parse_ip = |ip: &str| ip.parse::<IpAddr>().map_err(|_| invalid_header());
+    let parse_port = |port: &str| port.parse::<u16>().map_err(|_| invalid_header());
+
+    Ok(Some(ProxiedAddresses {
+        source: SocketAddr::new(parse_ip(src)?, parse_port(src_port)?),
+        destination: SocketAddr::new(parse_ip(dst)?, parse_port(dst_port)?),
+    }))
+}
+
+async fn read_v2<R: RawRead>(
+    reader: &mut R,
+) -> io::Result<Option<ProxiedAddresses>> {
+    const VERSION: u8 = 0x20;
+    const COMMAND_PROXY: u8 = 0x01;
+    const TCP4: u8 = 0x11;
+    const TCP6: u8 = 0x21;
+
+    let version_command = reader.read_u8().await?;
+    let family = reader.read_u8().await?;
+    let length = reader.read_u16().await? as usize;
+
+    if version_command & 0xF0 != VERSION {
+        return Err(invalid_header());
+    }
+
+    // Addresses are followed by TLVs, they're read, but ignored
+    let mut payload = vec![0; length];
+    reader.read_exact(&mut payload).await?;
+
+    if version_command & 0x0F != COMMAND_PROXY {
+        return Ok(None);
+    }
+
+    let (source, destination, ports) = match family {
+        TCP4 if length >= 12 => {
+            let ip = |at: usize| {
+                let octets: [u8; 4] = payload[at..at + 4].try_into().unwrap();
+                IpAddr::V4(Ipv4Addr::from(octets))
+            };
+            (ip(0), ip(4), &payload[8..12])
+        }
+
+        TCP6 if length >= 36 => {
+            let ip = |at: usize| {
+                let octets: [u8; 16] = payload[at..at + 16].try_into().unwrap();
+                IpAddr::V6(Ipv6Addr::from(octets))
+            };
+            (ip(0), ip(16), &payload[32..36])
+        }
+
+        TCP4 | TCP6 => return Err(invalid_header()),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(ProxiedAddresses {
+        source: SocketAddr::new(source, u16::from_be_bytes([ports[0], ports[1]])),
+        destination: SocketAddr::new(
+            destination,
+            u16::from_be_bytes([ports[2], ports[3]]),
+        ),
+    }))
+}
+
+fn invalid_header() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid PROXY protocol header")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addresses(source: &str, destination: &str) -> ProxiedAddresses {
+        ProxiedAddresses {
+            source: source.parse().unwrap(),
+            destination: destination.parse().unwrap(),
+        }
+    }
+
+    async fn read(mut header: &[u8]) -> io::Result<Option<ProxiedAddresses>> {
+        read_header(&mut header).await
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        for version in [ProxyVersion::V1, ProxyVersion::V2] {
+            for expected in [
+                addresses("192.0.2.1:4000", "198.51.100.7:443"),
+                addresses("[2001:db8::1]:4000", "[2001:db8::2]:443"),
+            ] {
+                let header = expected.encode(version);
+                assert_eq!(read(&header).await.unwrap(), Some(expected));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn mixed_families_are_mapped_to_ipv6() {
+        let header = addresses("192.0.2.1:4000", "[2001:db8::2]:443")
+            .encode(ProxyVersion::V2);
+
+        assert_eq!(
+            read(&header).await.unwrap(),
+            Some(addresses("[::ffff:192.0.2.1]:4000", "[2001:db8::2]:443"))
+        );
+    }
+
+    #[tokio::test]
+    async fn data_after_header_is_untouched() {
+        let mut stream =
+            addresses("192.0.2.1:4000", "198.51.100.7:443").encode(ProxyVersion::V1);
+        stream.extend_from_slice(b"payload");
+
+        let mut reader = &stream[..];
+        read_header(&mut reader).await.unwrap();
+        assert_eq!(reader, b"payload");
+    }
+
+    #[tokio::test]
+    async fn written_header_precedes_data() {
+        let expected = addresses("192.0.2.1:4000", "198.51.100.7:443");
+        for version in [ProxyVersion::V1, ProxyVersion::V2] {
+            for addresses in [Some(expected), None] {
+                let mut stream = Vec::new();
+                write_header(&mut stream, addresses, version)
+                    .await
+                    .unwrap();
+                stream.extend_from_slice(b"payload");
+
+                let mut reader = &stream[..];
+                assert_eq!(read_header(&mut reader).await.unwrap(), addresses);
+                assert_eq!(reader, b"payload");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn v1_unknown_carries_no_addresses() {
+        assert_eq!(read(b"PROXY UNKNOWN\r\n").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn v1_trailing_fields_are_rejected() {
+        let header = b"PROXY TCP4 192.0.2.1 198.51.100.7 4000 443 extra\r\n";
+        assert!(read(header).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn v1_malformed_headers_are_rejected() {
+        for header in [
+            &b"PROXY TCP4 192.0.2.1 198.51.100.7 4000\r\n"[..],
+            b"PROXY TCP4 192.0.2.1 198.51.100.7 4000 70000\r\n",
+            b"PROXY UDP4 192.0.2.1 198.51.100.7 4000 443\r\n",
+            &[b'P', b'R', b'O', b'X', b'Y', b' '].repeat(20),
+        ] {
+            assert!(read(header).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn v2_local_carries_no_addresses() {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x20, 0x00, 0x00, 0x00]);
+
+        assert_eq!(read(&header).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn v2_short_addresses_are_rejected() {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x21, 0x11, 0x00, 0x04, 192, 0, 2, 1]);
+
+        assert!(read(&header).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn unknown_signature_is_rejected() {
+        assert!(read(b"GET / HTTP/1.1\r\n").await.is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    path::PathBuf,
+    sync::Arc,
+};
+
+use tokio_rustls::rustls::{
+    client::danger::{
+        HandshakeSignatureValid,
+        ServerCertVerified,
+        ServerCertVerifier,
+    },
+    crypto::{
+        verify_tls12_signature,
+        verify_tls13_signature,
+        WebPkiSupportedAlgorithms,
+    },
+    pki_types::{
+        CertificateDer,
+        ServerName,
+        UnixTime,
+    },
+    ClientConfig,
+    DigitallySignedStruct,
+    Error,
+    RootCertStore,
+    SignatureScheme,
+};
+
+use super::{
+    load_certificates,
+    provider,
+    Fingerprint,
+    TlsResult,
+};
+
+/// How the client checks identity of the server
+#[derive(Debug, Clone)]
+pub enum ServerVerification {
+    /// Verify certificate chain against CA certificates
+    /// from the PEM file
+    Ca(PathBuf),
+
+    /// Accept only the exact leaf certificate, chain and
+    /// server name are not checked
+    Fingerprint(Fingerprint),
+}
+
+/// Creates client-side TLS configuration
+pub fn client_config(
+    verification: &ServerVerification,
+) -> TlsResult<Arc<ClientConfig>> {
+    let provider = provider();
+    let builder = ClientConfig::builder_with_provider(Arc::clone(&provider))
+        .with_safe_default_protocol_versions()?;
+
+    let config = match verification {
+        ServerVerification::Ca(path) => {
+            let mut roots = RootCertStore::empty();
+            for certificate in load_certificates(path)? {
+                roots.add(certificate)?;
+            }
+
+            builder
+                .with_root_certificates(roots)
+                .with_no_client_auth()
+        }
+
+        ServerVerification::Fingerprint(fingerprint) => builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(FingerprintVerifier {
+                fingerprint: *fingerprint,
+                algorithms: provider.signature_verification_algorithms,
+            }))
+            .with_no_client_auth(),
+    };
+
+    Ok(Arc::new(config))
+}
+
+#[derive(Debug)]
+struct FingerprintVerifier {
+    fingerprint: Fingerprint,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for FingerprintVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, Error> {
+        if Fingerprint::of(end_entity) == self.fingerprint {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(Error::General(
+                "server certificate does not match pinned fingerprint".to_owned(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
@@ -0,0 +1,216 @@
+//! TLS support for the tcpflux connections. Both sides use
+//! the `ring` crypto provider of the rustls.
+
+use std::{
+    fmt,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+};
+
+use sha2::{
+    Digest,
+    Sha256,
+};
+pub use tokio_rustls::rustls;
+use tokio_rustls::rustls::{
+    crypto::CryptoProvider,
+    pki_types::{
+        pem::PemObject,
+        CertificateDer,
+    },
+};
+
+use crate::error::TlsError;
+
+pub mod client;
+pub mod server;
+
+pub type TlsResult<T> = Result<T, TlsError>;
+
+/// SHA-256 fingerprint of the DER-encoded certificate.
+/// Displayed and parsed as colon-separated hex pairs
+/// (`AB:CD:...`), colons are optional when parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fingerprint(pub [u8; 32]);
+
+impl Fingerprint {
+    pub fn of(certificate: &CertificateDer<'_>) -> Self {
+        Self(Sha256::digest(certificate.as_ref()).into())
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, byte) in self.0.iter().enumerate() {
+            if idx != 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{byte:02X}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = TlsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s
+            .bytes()
+            .filter(|&c| c != b':')
+            .map(|c| {
+                (c as char)
+                    .to_digit(16)
+                    .ok_or(TlsError::InvalidFingerprint)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if digits.len() != 64 {
+            return Err(TlsError::InvalidFingerprint);
+        }
+
+        let mut fingerprint = [0; 32];
+        for (byte, pair) in fingerprint.iter_mut().zip(digits.chunks_exact(2)) {
+            *byte = ((pair[0] << 4) | pair[1]) as u8;
+        }
+
+        Ok(Self(fingerprint))
+    }
+}
+
+/// Reads all certificates from the PEM file
+pub fn load_certificates(
+    path: impl AsRef<Path>,
+) -> TlsResult<Vec<CertificateDer<'static>>> {
+    let certificates =
+        CertificateDer::pem_file_iter(path)?.collect::<Result<Vec<_>, _>>()?;
+    if certificates.is_empty() {
+        Err(TlsError::NoCertificates)
+    } else {
+        Ok(certificates)
+    }
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+    };
+    use tokio_rustls::rustls::pki_types::ServerName;
+
+    use super::{
+        client::{
+            client_config,
+            ServerVerification,
+        },
+        server::ServerTls,
+        *,
+    };
+    use crate::transport::{
+        memory::channel,
+        tls::{
+            TlsAcceptor,
+            TlsDialer,
+        },
+        Acceptor,
+        Dialer,
+    };
+
+    /// `openssl x509 -noout -fingerprint -sha256` of the
+    /// test certificate
+    const FINGERPRINT: &str = "D1:12:50:84:66:7A:E3:2B:E0:81:59:DC:AF:FF:9D:45:D3:\
+                               72:14:B2:62:99:5B:E0:20:ED:F5:85:22:4A:57:3C";
+
+    fn server_tls() -> ServerTls {
+        let testdata = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata");
+        ServerTls::load(
+            format!("{testdata}/cert.pem"),
+            format!("{testdata}/key.pem"),
+        )
+        .unwrap()
+    }
+
+    /// Handshakes over the memory transport, the client
+    /// pins the `pinned` fingerprint
+    async fn exchange(pinned: Fingerprint) -> std::io::Result<Vec<u8>> {
+        let (acceptor, dialer) = channel(4096);
+        let acceptor = TlsAcceptor::new(acceptor, server_tls().config);
+        let dialer = TlsDialer::new(
+            dialer,
+            client_config(&ServerVerification::Fingerprint(pinned)).unwrap(),
+            ServerName::try_from("localhost").unwrap(),
+        );
+
+        let server = async {
+            let (incoming, mut address) = acceptor.accept().await?;
+            let mut stream = acceptor.upgrade(incoming, &mut address).await?;
+            stream.write_all(b"pong").await?;
+            stream.flush().await
+        };
+        let client = async {
+            let mut stream = dialer.dial().await?;
+            let mut reply = vec![0; 4];
+            stream.read_exact(&mut reply).await?;
+            Ok::<_, std::io::Error>(reply)
+        };
+
+        let (server, client) = tokio::join!(server, client);
+        let reply = client?;
+        server?;
+        Ok(reply)
+    }
+
+    #[test]
+    fn fingerprint_round_trip() {
+        let fingerprint: Fingerprint = FINGERPRINT.parse().unwrap();
+
+        assert_eq!(fingerprint.to_string(), FINGERPRINT);
+        assert_eq!(
+            FINGERPRINT
+                .replace(':', "")
+                .to_lowercase()
+                .parse::<Fingerprint>()
+                .unwrap(),
+            fingerprint
+        );
+    }
+
+    #[test]
+    fn malformed_fingerprints_are_rejected() {
+        for fingerprint in [
+            "",
+            "AB:CD",
+            &FINGERPRINT[3..],
+            &FINGERPRINT.replace('D', "G"),
+        ] {
+            assert!(matches!(
+                fingerprint.parse::<Fingerprint>(),
+                Err(TlsError::InvalidFingerprint)
+            ));
+        }
+    }
+
+    #[test]
+    fn fingerprint_of_the_leaf_certificate() {
+        assert_eq!(server_tls().fingerprint, FINGERPRINT.parse().unwrap());
+    }
+
+    #[tokio::test]
+    async fn pinned_fingerprint_is_accepted() {
+        let reply = exchange(FINGERPRINT.parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(reply, b"pong");
+    }
+
+    #[tokio::test]
+    async fn other_fingerprint_is_rejected() {
+        assert!(exchange(Fingerprint([0; 32])).await.is_err());
+    }
+}
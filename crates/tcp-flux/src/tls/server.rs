@@ -0,0 +1,52 @@
+use std::{
+    path::Path,
+    sync::Arc,
+};
+
+use tokio_rustls::rustls::{
+    pki_types::{
+        pem::PemObject,
+        PrivateKeyDer,
+    },
+    ServerConfig,
+};
+
+use super::{
+    load_certificates,
+    provider,
+    Fingerprint,
+    TlsResult,
+};
+
+/// Loaded server-side TLS configuration
+pub struct ServerTls {
+    pub config: Arc<ServerConfig>,
+
+    /// Fingerprint of the leaf certificate, clients can pin
+    /// it instead of verifying the whole chain
+    pub fingerprint: Fingerprint,
+}
+
+impl ServerTls {
+    /// Loads certificate chain and private key from the PEM
+    /// files. The first certificate in the chain must be
+    /// the leaf one.
+    pub fn load(
+        certificate: impl AsRef<Path>,
+        key: impl AsRef<Path>,
+    ) -> TlsResult<Self> {
+        let chain = load_certificates(certificate)?;
+        let key = PrivateKeyDer::from_pem_file(key)?;
+        let fingerprint = Fingerprint::of(&chain[0]);
+
+        let config = ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(chain, key)?;
+
+        Ok(Self {
+            config: Arc::new(config),
+            fingerprint,
+        })
+    }
+}
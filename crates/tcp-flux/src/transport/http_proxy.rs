@@ -0,0 +1,175 @@
+use std::io;
+
+use tokio::{
+    io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+    },
+    net::{
+        TcpStream,
+        ToSocketAddrs,
+    },
+};
+
+use super::Dialer;
+
+/// Maximum size of the proxy response head
+const MAX_RESPONSE_SIZE: usize = 8192;
+
+/// Establishes TCP connection to the `target` through the
+/// HTTP proxy by issuing `CONNECT` request
+pub struct HttpConnectDialer<A> {
+    proxy: A,
+    target: String,
+    authorization: Option<String>,
+}
+
+impl<A> HttpConnectDialer<A> {
+    /// `target` is `host:port` of the tcpflux server
+    pub fn new(proxy: A, target: impl Into<String>) -> Self {
+        Self {
+            proxy,
+            target: target.into(),
+            authorization: None,
+        }
+    }
+
+    /// Sets value of the `Proxy-Authorization` header, e.g.
+    /// `Basic <credentials>`
+    pub fn with_authorization(self, authorization: impl Into<String>) -> Self {
+        Self {
+            authorization: Some(authorization.into()),
+            ..self
+        }
+    }
+}
+
+impl<A: ToSocketAddrs + Send + Sync> Dialer for HttpConnectDialer<A> {
+    type Stream = TcpStream;
+
+    async fn dial(&self) -> io::Result<TcpStream> {
+        let mut socket = TcpStream::connect(&self.proxy).await?;
+        socket.set_nodelay(true)?;
+
+        let mut request =
+            format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", self.target);
+        if let Some(ref authorization) = self.authorization {
+            request += &format!("Proxy-Authorization: {authorization}\r\n");
+        }
+        request += "\r\n";
+        socket.write_all(request.as_bytes()).await?;
+
+        let head = read_response_head(&mut socket).await?;
+        let status = head
+            .split(|&c| c == b' ')
+            .nth(1)
+            .and_then(|s| std::str::from_utf8(s).ok());
+        match status {
+            Some("200") => Ok(socket),
+            _ => Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!(
+                    "proxy refused to connect: {}",
+                    String::from_utf8_lossy(
+                        head.split(|&c| c == b'\r')
+                            .next()
+                            .unwrap_or_default()
+                    )
+                ),
+            )),
+        }
+    }
+}
+
+/// Reads response up to the empty line. Reads byte by byte,
+/// since anything after the head belongs to the tunnel.
+async fn read_response_head(socket: &mut TcpStream) -> io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(128);
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() == MAX_RESPONSE_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "proxy response is too large",
+            ));
+        }
+        head.push(socket.read_u8().await?);
+    }
+
+    Ok(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Proxy answering the single `CONNECT` with the
+    /// `response`, returns the request it got
+    async fn proxy(
+        response: &'static [u8],
+    ) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let task = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(socket.read_u8().await.unwrap());
+            }
+            // Client may hang up before reading everything
+            _ = socket.write_all(response).await;
+
+            String::from_utf8(request).unwrap()
+        });
+        (address, task)
+    }
+
+    #[tokio::test]
+    async fn tunnel_starts_right_after_the_head() {
+        let (address, request) =
+            proxy(b"HTTP/1.1 200 Connection established\r\nVia: test\r\n\r\ntunnel")
+                .await;
+        let dialer = HttpConnectDialer::new(address, "flux.example:28305")
+            .with_authorization("Basic dXNlcjpwdw==");
+
+        let mut socket = dialer.dial().await.unwrap();
+        let mut tunnel = [0; 6];
+        socket.read_exact(&mut tunnel).await.unwrap();
+        assert_eq!(&tunnel, b"tunnel");
+
+        assert_eq!(
+            request.await.unwrap(),
+            "CONNECT flux.example:28305 HTTP/1.1\r\nHost: \
+             flux.example:28305\r\nProxy-Authorization: Basic dXNlcjpwdw==\r\n\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn refusal_carries_the_status_line() {
+        let (address, _request) =
+            proxy(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n").await;
+
+        let error = HttpConnectDialer::new(address, "flux.example:28305")
+            .dial()
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+        assert!(error
+            .to_string()
+            .ends_with("HTTP/1.1 407 Proxy Authentication Required"));
+    }
+
+    #[tokio::test]
+    async fn oversized_head_is_rejected() {
+        static HEAD: [u8; MAX_RESPONSE_SIZE + 1] = [b'a'; MAX_RESPONSE_SIZE + 1];
+        let (address, _request) = proxy(&HEAD).await;
+
+        let error = HttpConnectDialer::new(address, "flux.example:28305")
+            .dial()
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
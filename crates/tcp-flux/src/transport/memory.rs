@@ -0,0 +1,163 @@
+//! In-memory transport, mostly useful for testing without
+//! touching the network
+
+use std::{
+    io,
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+        Arc,
+    },
+};
+
+use tokio::{
+    io::DuplexStream,
+    sync::{
+        mpsc,
+        Mutex,
+    },
+};
+
+use super::{
+    Acceptor,
+    Dialer,
+    PeerAddress,
+};
+
+/// Creates connected acceptor and dialer. `buffer_size` is
+/// the size of the in-memory buffer of each connection
+pub fn channel(buffer_size: usize) -> (MemoryAcceptor, MemoryDialer) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    (
+        MemoryAcceptor { rx: Mutex::new(rx) },
+        MemoryDialer {
+            tx,
+            buffer_size,
+            next_id: Arc::default(),
+        },
+    )
+}
+
+pub struct MemoryAcceptor {
+    rx: Mutex<mpsc::UnboundedReceiver<(DuplexStream, u64)>>,
+}
+
+impl Acceptor for MemoryAcceptor {
+    type Incoming = DuplexStream;
+    type Stream = DuplexStream;
+
+    async fn accept(&self) -> io::Result<(DuplexStream, PeerAddress)> {
+        match self.rx.lock().await.recv().await {
+            Some((stream, id)) => Ok((stream, PeerAddress::Memory { id })),
+            None => Err(io::ErrorKind::NotConnected.into()),
+        }
+    }
+
+    async fn upgrade(
+        &self,
+        incoming: DuplexStream,
+        _address: &mut PeerAddress,
+    ) -> io::Result<DuplexStream> {
+        Ok(incoming)
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryDialer {
+    tx: mpsc::UnboundedSender<(DuplexStream, u64)>,
+    buffer_size: usize,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Dialer for MemoryDialer {
+    type Stream = DuplexStream;
+
+    async fn dial(&self) -> io::Result<DuplexStream> {
+        let (client, server) = tokio::io::duplex(self.buffer_size);
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        self.tx
+            .send((server, id))
+            .map_err(|_| io::ErrorKind::ConnectionRefused)?;
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use tokio::io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+    };
+
+    use super::*;
+    use crate::{
+        connection::any::ConnectionType,
+        connector::Connector,
+        listener::Listener,
+    };
+
+    #[tokio::test]
+    async fn handshake_round_trip() {
+        let (acceptor, dialer) = channel(64);
+        let listener = Listener::new(acceptor);
+        let connector = Connector::new(dialer);
+
+        for type_ in [
+            ConnectionType::Master,
+            ConnectionType::Flow {
+                port: 31000,
+                secret: [0xAB; 16],
+                id: NonZeroU32::new(0xDEAD_BEEF),
+            },
+            ConnectionType::Flow {
+                port: 31000,
+                secret: [0xCD; 16],
+                id: None,
+            },
+        ] {
+            let mut client = connector.connect(type_).await.unwrap();
+            let incoming = listener.next_connection().await.unwrap();
+            let mut connection = incoming.handshake().await.unwrap();
+            assert_eq!(connection.type_, type_);
+
+            client.write_all(b"ping").await.unwrap();
+            let mut buf = [0; 4];
+            connection
+                .socket
+                .read_exact(&mut buf)
+                .await
+                .unwrap();
+            assert_eq!(&buf, b"ping");
+        }
+    }
+
+    #[tokio::test]
+    async fn peers_get_distinct_addresses() {
+        let (acceptor, dialer) = channel(64);
+        let listener = Listener::new(acceptor);
+
+        let _first = dialer.dial().await.unwrap();
+        let _second = dialer.dial().await.unwrap();
+        let first = listener.next_connection().await.unwrap();
+        let second = listener.next_connection().await.unwrap();
+
+        assert_ne!(first.address, second.address);
+    }
+
+    #[tokio::test]
+    async fn wrong_protocol_is_rejected() {
+        let (acceptor, dialer) = channel(64);
+        let listener = Listener::new(acceptor);
+
+        let mut client = dialer.dial().await.unwrap();
+        client.write_u8(b'x').await.unwrap();
+
+        let incoming = listener.next_connection().await.unwrap();
+        assert!(incoming.handshake().await.is_err());
+    }
+}
@@ -0,0 +1,57 @@
+//! Transports that tcpflux connections can be carried
+//! over. Server side accepts connections through the
+//! [`Acceptor`], client side establishes them through the
+//! [`Dialer`].
+
+use std::{
+    future::Future,
+    io,
+};
+
+pub use flux_common::address::PeerAddress;
+use tokio::io::{
+    AsyncRead,
+    AsyncWrite,
+};
+
+pub mod http_proxy;
+pub mod memory;
+pub mod proxied;
+pub mod tcp;
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(unix)]
+pub mod unix;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+pub trait Transport: AsyncRead + AsyncWrite + Unpin + Send + 'static {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + 'static> Transport for T {}
+
+pub trait Acceptor: Send + Sync + 'static {
+    /// Connection that was accepted, but not yet upgraded
+    type Incoming: Send + 'static;
+    type Stream: Transport;
+
+    /// Accepts next connection. Should complete as soon as
+    /// connection is accepted, any exchange with the peer
+    /// must be left to the [`Acceptor::upgrade`]
+    fn accept(
+        &self,
+    ) -> impl Future<Output = io::Result<(Self::Incoming, PeerAddress)>> + Send;
+
+    /// Makes accepted connection ready for use (performs
+    /// handshakes, etc.). `address` may be refined if the
+    /// transport knows better where connection came from.
+    fn upgrade(
+        &self,
+        incoming: Self::Incoming,
+        address: &mut PeerAddress,
+    ) -> impl Future<Output = io::Result<Self::Stream>> + Send;
+}
+
+pub trait Dialer: Send + Sync {
+    type Stream: Transport;
+
+    fn dial(&self) -> impl Future<Output = io::Result<Self::Stream>> + Send;
+}
@@ -0,0 +1,47 @@
+use std::io;
+
+use super::{
+    Acceptor,
+    PeerAddress,
+};
+use crate::proxy_protocol::read_header;
+
+/// Reads PROXY protocol header (v1 or v2) sent by the load
+/// balancer in front of the inner acceptor and replaces
+/// peer address with the real one. Connections without the
+/// header are rejected.
+pub struct ProxyProtocolAcceptor<A> {
+    inner: A,
+}
+
+impl<A> ProxyProtocolAcceptor<A> {
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+
+    pub const fn inner_ref(&self) -> &A {
+        &self.inner
+    }
+}
+
+impl<A: Acceptor> Acceptor for ProxyProtocolAcceptor<A> {
+    type Incoming = A::Incoming;
+    type Stream = A::Stream;
+
+    async fn accept(&self) -> io::Result<(Self::Incoming, PeerAddress)> {
+        self.inner.accept().await
+    }
+
+    async fn upgrade(
+        &self,
+        incoming: Self::Incoming,
+        address: &mut PeerAddress,
+    ) -> io::Result<Self::Stream> {
+        let mut stream = self.inner.upgrade(incoming, address).await?;
+        if let Some(addresses) = read_header(&mut stream).await? {
+            *address = PeerAddress::Tcp(addresses.source);
+        }
+
+        Ok(stream)
+    }
+}
@@ -0,0 +1,76 @@
+use std::{
+    io,
+    net::SocketAddr,
+};
+
+use tokio::net::{
+    TcpListener,
+    TcpStream,
+    ToSocketAddrs,
+};
+
+use super::{
+    Acceptor,
+    Dialer,
+    PeerAddress,
+};
+
+pub struct TcpAcceptor {
+    handle: TcpListener,
+}
+
+impl TcpAcceptor {
+    pub async fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        TcpListener::bind(address).await.map(Self::from)
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.handle.local_addr()
+    }
+}
+
+impl From<TcpListener> for TcpAcceptor {
+    fn from(handle: TcpListener) -> Self {
+        Self { handle }
+    }
+}
+
+impl Acceptor for TcpAcceptor {
+    type Incoming = TcpStream;
+    type Stream = TcpStream;
+
+    async fn accept(&self) -> io::Result<(TcpStream, PeerAddress)> {
+        let (socket, address) = self.handle.accept().await?;
+        Ok((socket, PeerAddress::Tcp(address)))
+    }
+
+    async fn upgrade(
+        &self,
+        incoming: TcpStream,
+        _address: &mut PeerAddress,
+    ) -> io::Result<TcpStream> {
+        incoming.set_nodelay(true)?;
+        Ok(incoming)
+    }
+}
+
+pub struct TcpDialer<A> {
+    address: A,
+}
+
+impl<A> TcpDialer<A> {
+    pub const fn new(address: A) -> Self {
+        Self { address }
+    }
+}
+
+impl<A: ToSocketAddrs + Send + Sync> Dialer for TcpDialer<A> {
+    type Stream = TcpStream;
+
+    async fn dial(&self) -> io::Result<TcpStream> {
+        let socket = TcpStream::connect(&self.address).await?;
+        socket.set_nodelay(true)?;
+
+        Ok(socket)
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    io,
+    sync::Arc,
+};
+
+use tokio_rustls::{
+    client,
+    rustls::{
+        pki_types::ServerName,
+        ClientConfig,
+        ServerConfig,
+    },
+    server,
+    TlsAcceptor as RustlsAcceptor,
+    TlsConnector,
+};
+
+use super::{
+    Acceptor,
+    Dialer,
+    PeerAddress,
+};
+
+/// Wraps connections of the inner acceptor into the TLS
+/// session
+pub struct TlsAcceptor<A> {
+    inner: A,
+    acceptor: RustlsAcceptor,
+}
+
+impl<A> TlsAcceptor<A> {
+    pub fn new(inner: A, config: Arc<ServerConfig>) -> Self {
+        Self {
+            inner,
+            acceptor: RustlsAcceptor::from(config),
+        }
+    }
+
+    pub const fn inner_ref(&self) -> &A {
+        &self.inner
+    }
+}
+
+impl<A: Acceptor> Acceptor for TlsAcceptor<A> {
+    type Incoming = A::Incoming;
+    type Stream = server::TlsStream<A::Stream>;
+
+    async fn accept(&self) -> io::Result<(Self::Incoming, PeerAddress)> {
+        self.inner.accept().await
+    }
+
+    async fn upgrade(
+        &self,
+        incoming: Self::Incoming,
+        address: &mut PeerAddress,
+    ) -> io::Result<Self::Stream> {
+        let stream = self.inner.upgrade(incoming, address).await?;
+        self.acceptor.accept(stream).await
+    }
+}
+
+/// Performs TLS handshake over the connection of the
+/// inner dialer. `server_name` is sent as SNI and checked
+/// against the certificate (unless the fingerprint is
+/// pinned)
+pub struct TlsDialer<D> {
+    inner: D,
+    connector: TlsConnector,
+    server_name: ServerName<'static>,
+}
+
+impl<D> TlsDialer<D> {
+    pub fn new(
+        inner: D,
+        config: Arc<ClientConfig>,
+        server_name: ServerName<'static>,
+    ) -> Self {
+        Self {
+            inner,
+            connector: TlsConnector::from(config),
+            server_name,
+        }
+    }
+}
+
+impl<D: Dialer> Dialer for TlsDialer<D> {
+    type Stream = client::TlsStream<D::Stream>;
+
+    async fn dial(&self) -> io::Result<Self::Stream> {
+        let stream = self.inner.dial().await?;
+        self.connector
+            .connect(self.server_name.clone(), stream)
+            .await
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    fs,
+    io,
+    os::unix::{
+        fs::{
+            FileTypeExt,
+            PermissionsExt,
+        },
+        net::{
+            UnixListener as StdUnixListener,
+            UnixStream as StdUnixStream,
+        },
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use tokio::net::{
+    UnixListener,
+    UnixStream,
+};
+
+use super::{
+    Acceptor,
+    Dialer,
+    PeerAddress,
+};
+
+pub struct UnixAcceptor {
+    handle: UnixListener,
+
+    /// Socket file to remove when the acceptor is dropped
+    owned_path: Option<PathBuf>,
+}
+
+impl UnixAcceptor {
+    /// Binds the socket on the `path`. If there is a socket
+    /// file left from the previous run and nobody listens
+    /// on it, it is removed first.
+    pub fn bind(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let listener = bind_std(path)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(Self {
+            handle: UnixListener::from_std(listener)?,
+            owned_path: Some(path.to_owned()),
+        })
+    }
+
+    /// Sets permission bits of the socket file, e.g.
+    /// `0o660`
+    pub fn set_mode(&self, mode: u32) -> io::Result<()> {
+        match self.owned_path {
+            Some(ref path) => {
+                fs::set_permissions(path, fs::Permissions::from_mode(mode))
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub const fn inner_ref(&self) -> &UnixListener {
+        &self.handle
+    }
+}
+
+/// Same as the [`UnixAcceptor::bind`], but does not require
+/// the runtime. Socket file is not removed automatically.
+pub fn bind_std(path: impl AsRef<Path>) -> io::Result<StdUnixListener> {
+    let path = path.as_ref();
+    remove_stale(path)?;
+
+    StdUnixListener::bind(path)
+}
+
+fn remove_stale(path: &Path) -> io::Result<()> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(m) => m,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    if !metadata.file_type().is_socket() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket", path.display()),
+        ));
+    }
+
+    match StdUnixStream::connect(path) {
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("{} is in use by another process", path.display()),
+        )),
+        Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => {
+            fs::remove_file(path)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+impl From<UnixListener> for UnixAcceptor {
+    fn from(handle: UnixListener) -> Self {
+        Self {
+            handle,
+            owned_path: None,
+        }
+    }
+}
+
+impl Drop for UnixAcceptor {
+    fn drop(&mut self) {
+        if let Some(ref path) = self.owned_path {
+            _ = fs::remove_file(path);
+        }
+    }
+}
+
+impl Acceptor for UnixAcceptor {
+    type Incoming = UnixStream;
+    type Stream = UnixStream;
+
+    async fn accept(&self) -> io::Result<(UnixStream, PeerAddress)> {
+        let (socket, _) = self.handle.accept().await?;
+        let credentials = socket.peer_cred()?;
+
+        Ok((
+            socket,
+            PeerAddress::Unix {
+                pid: credentials.pid(),
+                uid: credentials.uid(),
+                gid: credentials.gid(),
+            },
+        ))
+    }
+
+    async fn upgrade(
+        &self,
+        incoming: UnixStream,
+        _address: &mut PeerAddress,
+    ) -> io::Result<UnixStream> {
+        Ok(incoming)
+    }
+}
+
+pub struct UnixDialer {
+    path: PathBuf,
+}
+
+impl UnixDialer {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Dialer for UnixDialer {
+    type Stream = UnixStream;
+
+    async fn dial(&self) -> io::Result<UnixStream> {
+        UnixStream::connect(&self.path).await
+    }
+}
@@ -0,0 +1,277 @@
+//! Carries tcpflux connections over the WebSocket: each
+//! write is sent as the binary message, payloads of the
+//! received binary messages are read back as the byte
+//! stream. Useful when only HTTP(S) traffic is allowed.
+
+use std::{
+    io,
+    pin::Pin,
+    task::{
+        ready,
+        Context,
+        Poll,
+    },
+};
+
+use futures_util::{
+    Sink,
+    Stream,
+};
+use tokio::io::{
+    AsyncRead,
+    AsyncWrite,
+    ReadBuf,
+};
+use tokio_tungstenite::{
+    tungstenite::{
+        handshake::server::{
+            ErrorResponse,
+            Request,
+            Response,
+        },
+        http::StatusCode,
+        Bytes,
+        Message,
+    },
+    WebSocketStream,
+};
+
+use super::{
+    Acceptor,
+    Dialer,
+    PeerAddress,
+    Transport,
+};
+
+/// Accepts WebSocket connections on the specific HTTP path
+/// over the inner acceptor
+pub struct WsAcceptor<A> {
+    inner: A,
+    path: String,
+}
+
+impl<A> WsAcceptor<A> {
+    pub fn new(inner: A, path: impl Into<String>) -> Self {
+        Self {
+            inner,
+            path: path.into(),
+        }
+    }
+
+    pub const fn inner_ref(&self) -> &A {
+        &self.inner
+    }
+}
+
+impl<A: Acceptor> Acceptor for WsAcceptor<A> {
+    type Incoming = A::Incoming;
+    type Stream = WsStream<A::Stream>;
+
+    async fn accept(&self) -> io::Result<(Self::Incoming, PeerAddress)> {
+        self.inner.accept().await
+    }
+
+    async fn upgrade(
+        &self,
+        incoming: Self::Incoming,
+        address: &mut PeerAddress,
+    ) -> io::Result<Self::Stream> {
+        let stream = self.inner.upgrade(incoming, address).await?;
+
+        // Response type is dictated by the tungstenite
+        #[allow(clippy::result_large_err)]
+        let check_path = |request: &Request, response: Response| {
+            if request.uri().path() == self.path {
+                Ok(response)
+            } else {
+                let mut response = ErrorResponse::new(None);
+                *response.status_mut() = StatusCode::NOT_FOUND;
+                Err(response)
+            }
+        };
+
+        tokio_tungstenite::accept_hdr_async(stream, check_path)
+            .await
+            .map(WsStream::new)
+            .map_err(io::Error::other)
+    }
+}
+
+/// Performs WebSocket handshake over the connection of the
+/// inner dialer. `url` is used to fill the request
+/// (`Host` header and the path), connection itself is made
+/// by the inner dialer.
+pub struct WsDialer<D> {
+    inner: D,
+    url: String,
+}
+
+impl<D> WsDialer<D> {
+    pub fn new(inner: D, url: impl Into<String>) -> Self {
+        Self {
+            inner,
+            url: url.into(),
+        }
+    }
+}
+
+impl<D: Dialer> Dialer for WsDialer<D> {
+    type Stream = WsStream<D::Stream>;
+
+    async fn dial(&self) -> io::Result<Self::Stream> {
+        let stream = self.inner.dial().await?;
+        tokio_tungstenite::client_async(self.url.as_str(), stream)
+            .await
+            .map(|(stream, _)| WsStream::new(stream))
+            .map_err(io::Error::other)
+    }
+}
+
+/// Byte stream over the WebSocket
+pub struct WsStream<S> {
+    inner: WebSocketStream<S>,
+
+    /// Unread part of the last received message
+    read_buf: Bytes,
+
+    /// Length of the written, but not yet flushed buffer
+    in_flight: Option<usize>,
+}
+
+impl<S> WsStream<S> {
+    const fn new(inner: WebSocketStream<S>) -> Self {
+        Self {
+            inner,
+            read_buf: Bytes::new(),
+            in_flight: None,
+        }
+    }
+}
+
+impl<S: Transport> AsyncRead for WsStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        while this.read_buf.is_empty() {
+            match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+                Some(Ok(Message::Binary(data))) => this.read_buf = data,
+                Some(Ok(Message::Close(..))) | None => return Poll::Ready(Ok(())),
+
+                // Pings are answered by the tungstenite itself
+                Some(Ok(..)) => {}
+                Some(Err(e)) => return Poll::Ready(Err(io::Error::other(e))),
+            }
+        }
+
+        let read = this.read_buf.len().min(buf.remaining());
+        buf.put_slice(&this.read_buf[..read]);
+        this.read_buf = this.read_buf.slice(read..);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: Transport> AsyncWrite for WsStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let mut inner = Pin::new(&mut this.inner);
+
+        // Message is flushed right away, so the peer won't wait
+        // for the next write to get it
+        if this.in_flight.is_none() {
+            ready!(inner.as_mut().poll_ready(cx)).map_err(io::Error::other)?;
+            inner
+                .as_mut()
+                .start_send(Message::Binary(Bytes::copy_from_slice(buf)))
+                .map_err(io::Error::other)?;
+            this.in_flight = Some(buf.len());
+        }
+
+        ready!(inner.poll_flush(cx)).map_err(io::Error::other)?;
+        Poll::Ready(Ok(this.in_flight.take().unwrap_or_default()))
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner)
+            .poll_flush(cx)
+            .map_err(io::Error::other)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner)
+            .poll_close(cx)
+            .map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+        DuplexStream,
+    };
+
+    use super::*;
+    use crate::transport::memory::channel;
+
+    async fn connect(
+        url: &str,
+    ) -> io::Result<(WsStream<DuplexStream>, WsStream<DuplexStream>)> {
+        let (acceptor, dialer) = channel(4096);
+        let acceptor = WsAcceptor::new(acceptor, "/flux");
+        let dialer = WsDialer::new(dialer, url);
+
+        let server = async {
+            let (incoming, mut address) = acceptor.accept().await?;
+            acceptor.upgrade(incoming, &mut address).await
+        };
+        let (server, client) = tokio::join!(server, dialer.dial());
+        Ok((server?, client?))
+    }
+
+    #[tokio::test]
+    async fn reads_never_cross_messages() {
+        let (mut server, mut client) = connect("ws://localhost/flux").await.unwrap();
+        client.write_all(b"hello").await.unwrap();
+        client.write_all(b"world").await.unwrap();
+
+        let mut chunks = Vec::new();
+        let mut buf = [0; 3];
+        while chunks.len() < 4 {
+            let read = server.read(&mut buf).await.unwrap();
+            chunks.push(buf[..read].to_vec());
+        }
+
+        assert_eq!(chunks, [&b"hel"[..], b"lo", b"wor", b"ld"]);
+    }
+
+    #[tokio::test]
+    async fn shutdown_is_read_as_eof() {
+        let (mut server, mut client) = connect("ws://localhost/flux").await.unwrap();
+        client.write_all(b"bye").await.unwrap();
+        client.shutdown().await.unwrap();
+
+        let mut received = Vec::new();
+        server.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received, b"bye");
+    }
+
+    #[tokio::test]
+    async fn other_paths_are_rejected() {
+        assert!(connect("ws://localhost/other").await.is_err());
+    }
+}
@@ -21,4 +21,27 @@ pub enum ErrorCode {
 
     #[error("proxy server was shut")]
     Shutdown = 0x05,
+
+    #[error("requested port is already in use")]
+    PortUnavailable = 0x06,
+
+    #[error("disconnected by the server administrator")]
+    Disconnected = 0x07,
+
+    #[error("data quota of the user is exceeded")]
+    QuotaExceeded = 0x08,
+
+    #[error("session already holds the maximum number of proxies")]
+    TooManyProxies = 0x09,
+
+    #[error("user already has the maximum number of sessions")]
+    TooManySessions = 0x0A,
+
+    #[error("connection refused, proxy has the maximum number of flows")]
+    TooManyFlows = 0x0B,
+
+    #[error(
+        "connection refused, proxy has the maximum number of pending connections"
+    )]
+    TooManyPending = 0x0C,
 }
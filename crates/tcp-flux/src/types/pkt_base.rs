@@ -20,6 +20,9 @@ pub enum PktType {
 
     Authenticate = 0x04,
     UpdateRights = 0x05,
+    Usage        = 0x06,
+    Quota        = 0x07,
+    Expired      = 0x08,
 
     CreateTcp    = 0x0F,
     CreateHttp   = 0x10,
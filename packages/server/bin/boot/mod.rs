@@ -1,2 +1,6 @@
+pub mod reload;
 pub mod runner;
 pub mod setup;
+pub mod signals;
+pub mod systemd;
+pub mod upgrade;
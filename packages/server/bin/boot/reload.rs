@@ -0,0 +1,119 @@
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use color_eyre::eyre;
+use fluxus::config::{
+    handle::ConfigHandle,
+    reload::{
+        ReloadOutcome,
+        ReloadRequest,
+    },
+    root::Config,
+};
+use tokio::sync::mpsc;
+
+use super::setup::logging::LogLevelHandle;
+
+/// Reloads config on every SIGHUP and reload request.
+/// Invalid config is rejected as a whole, leaving the
+/// current one in place. `on_reload` is called once the
+/// new config is in place
+pub async fn run_reloader(
+    path: PathBuf,
+    config: ConfigHandle,
+    log_level: LogLevelHandle,
+    mut requests: mpsc::Receiver<ReloadRequest>,
+    on_reload: impl Fn(),
+) -> eyre::Result<()> {
+    let mut hangup = hangup()?;
+    loop {
+        let reply = tokio::select! {
+            Some(()) = hangup.recv() => {
+                tracing::info!("Received SIGHUP, reloading {}", path.display());
+                None
+            }
+
+            Some(reply) = requests.recv() => {
+                tracing::info!("Reload was requested, reloading {}", path.display());
+                Some(reply)
+            }
+
+            else => return Ok(()),
+        };
+
+        let outcome = reload(&path, &config, &log_level).await;
+        if outcome.is_ok() {
+            on_reload();
+        }
+        if let Some(reply) = reply {
+            _ = reply.send(outcome);
+        }
+    }
+}
+
+async fn reload(
+    path: &Path,
+    config: &ConfigHandle,
+    log_level: &LogLevelHandle,
+) -> ReloadOutcome {
+    let path = path.to_owned();
+    let loaded = tokio::task::spawn_blocking(move || Config::try_load(path))
+        .await
+        .map_err(eyre::Report::from)
+        .and_then(|loaded| loaded);
+    let new_config = match loaded {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::error!("Config was not reloaded: {e}");
+            return Err(e.to_string());
+        }
+    };
+    if let Err(e) = log_level.set(new_config.logging.level) {
+        tracing::error!("{e}");
+    }
+
+    let restart_required = config.replace(new_config);
+    for section in &restart_required {
+        tracing::error!("{section} was changed, restart is required to apply it");
+    }
+    tracing::info!("Config reloaded");
+
+    Ok(restart_required)
+}
+
+#[cfg(unix)]
+struct Hangup(tokio::signal::unix::Signal);
+
+#[cfg(unix)]
+fn hangup() -> std::io::Result<Hangup> {
+    use tokio::signal::unix::{
+        signal,
+        SignalKind,
+    };
+
+    signal(SignalKind::hangup()).map(Hangup)
+}
+
+#[cfg(unix)]
+impl Hangup {
+    async fn recv(&mut self) -> Option<()> {
+        self.0.recv().await
+    }
+}
+
+#[cfg(not(unix))]
+struct Hangup;
+
+#[cfg(not(unix))]
+fn hangup() -> std::io::Result<Hangup> {
+    Ok(Hangup)
+}
+
+#[cfg(not(unix))]
+impl Hangup {
+    async fn recv(&mut self) -> Option<()> {
+        std::future::pending().await
+    }
+}
@@ -1,15 +1,18 @@
 use std::{
-    path::Path,
+    path::{
+        Path,
+        PathBuf,
+    },
     process,
 };
 
 use color_eyre::eyre;
 use fluxus::config::root::Config;
 
-fn search_in_paths(paths: &[&Path]) -> Config {
+fn search_in_paths(paths: &[&Path]) -> (PathBuf, Config) {
     for path in paths {
         if let Ok(config) = Config::try_load(path) {
-            return config;
+            return (path.to_path_buf(), config);
         }
     }
 
@@ -20,9 +23,12 @@ fn search_in_paths(paths: &[&Path]) -> Config {
     process::exit(1)
 }
 
-pub fn load_config(path: Option<&Path>) -> eyre::Result<Config> {
+/// Loads config, returns it along with the path it was
+/// loaded from
+pub fn load_config(path: Option<&Path>) -> eyre::Result<(PathBuf, Config)> {
     match path {
-        Some(exact_path) => Config::try_load(exact_path),
+        Some(exact_path) => Config::try_load(exact_path)
+            .map(|config| (exact_path.to_path_buf(), config)),
         None => Ok(search_in_paths(&[
             Path::new("/etc/fluxus.toml"),
             Path::new("./fluxus.toml"),
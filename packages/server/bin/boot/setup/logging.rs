@@ -6,32 +6,46 @@ use fluxus::config::logging::{
     LogLevel,
     LoggingConfig,
 };
-use tracing::Level;
-use tracing_subscriber::FmtSubscriber;
+use tracing_subscriber::{
+    filter::LevelFilter,
+    fmt,
+    layer::SubscriberExt,
+    reload,
+    util::SubscriberInitExt,
+    Registry,
+};
 
-fn convert_config_level(level: LogLevel) -> Option<Level> {
-    use LogLevel as L;
-    Some(match level {
-        L::Info => Level::INFO,
-        L::Error => Level::ERROR,
-        L::Debug => Level::DEBUG,
-        L::Disabled => return None,
-    })
+/// Changes log level of the installed subscriber
+pub struct LogLevelHandle {
+    handle: reload::Handle<LevelFilter, Registry>,
+}
+
+impl LogLevelHandle {
+    pub fn set(&self, level: LogLevel) -> eyre::Result<()> {
+        self.handle
+            .reload(convert_config_level(level))
+            .wrap_err("failed to change log level")
+    }
 }
 
-pub fn install_tracing(config: &LoggingConfig) -> eyre::Result<()> {
-    let Some(level) = convert_config_level(config.level) else {
+const fn convert_config_level(level: LogLevel) -> LevelFilter {
+    use LogLevel as L;
+    match level {
+        L::Info => LevelFilter::INFO,
+        L::Error => LevelFilter::ERROR,
+        L::Debug => LevelFilter::DEBUG,
         // Logging is turned off
-        return Ok(());
-    };
+        L::Disabled => LevelFilter::OFF,
+    }
+}
 
-    let sub = FmtSubscriber::builder()
-        .with_max_level(level)
-        .without_time()
-        .compact()
-        .finish();
-    tracing::subscriber::set_global_default(sub)
+pub fn install_tracing(config: &LoggingConfig) -> eyre::Result<LogLevelHandle> {
+    let (filter, handle) = reload::Layer::new(convert_config_level(config.level));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().without_time().compact())
+        .try_init()
         .wrap_err("failed to set up global subscriber")?;
 
-    Ok(())
+    Ok(LogLevelHandle { handle })
 }
@@ -1,4 +1,5 @@
 pub mod configuration;
 pub mod environment;
 pub mod logging;
+pub mod privileges;
 pub mod runtime;
@@ -0,0 +1,98 @@
+use color_eyre::eyre;
+use fluxus::config::runtime::RuntimeConfig;
+
+/// Switches to the `runtime.user` and `runtime.group`. Must
+/// be called before any threads are spawned: capabilities
+/// are per-thread.
+#[cfg(unix)]
+pub fn drop_privileges(config: &RuntimeConfig) -> eyre::Result<()> {
+    use nix::unistd::{
+        setgid,
+        setgroups,
+        setuid,
+        Group,
+        User,
+    };
+
+    let Some(ref user_name) = config.user else {
+        return Ok(());
+    };
+
+    let user = User::from_name(user_name)?
+        .ok_or_else(|| eyre::eyre!("user {user_name:?} does not exist"))?;
+    let gid = match config.group {
+        Some(ref group_name) => {
+            Group::from_name(group_name)?
+                .ok_or_else(|| eyre::eyre!("group {group_name:?} does not exist"))?
+                .gid
+        }
+        None => user.gid,
+    };
+
+    if config.keep_net_bind_service {
+        keep_capabilities(true)?;
+    }
+
+    setgroups(&[gid])?;
+    setgid(gid)?;
+    setuid(user.uid)?;
+
+    if config.keep_net_bind_service {
+        restore_net_bind_service()?;
+        keep_capabilities(false)?;
+    }
+
+    tracing::info!(
+        "Switched to the uid {} and gid {gid}{}",
+        user.uid,
+        if config.keep_net_bind_service {
+            " keeping CAP_NET_BIND_SERVICE"
+        } else {
+            ""
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn keep_capabilities(keep: bool) -> eyre::Result<()> {
+    caps::securebits::set_keepcaps(keep)?;
+    Ok(())
+}
+
+/// Capabilities are kept in the permitted set only after
+/// switching the user, re-raises the single one
+#[cfg(target_os = "linux")]
+fn restore_net_bind_service() -> eyre::Result<()> {
+    use caps::{
+        CapSet,
+        Capability,
+        CapsHashSet,
+    };
+
+    let retained = CapsHashSet::from([Capability::CAP_NET_BIND_SERVICE]);
+    caps::set(None, CapSet::Permitted, &retained)?;
+    caps::set(None, CapSet::Effective, &retained)?;
+
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn keep_capabilities(_keep: bool) -> eyre::Result<()> {
+    eyre::bail!("runtime.keep_net_bind_service is supported only on linux")
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn restore_net_bind_service() -> eyre::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn drop_privileges(config: &RuntimeConfig) -> eyre::Result<()> {
+    if config.user.is_some() {
+        eyre::bail!("runtime.user is not supported on this platform");
+    }
+
+    Ok(())
+}
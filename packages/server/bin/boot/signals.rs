@@ -0,0 +1,25 @@
+use std::io;
+
+/// Waits for the SIGINT or SIGTERM, returns name of the
+/// received signal
+#[cfg(unix)]
+pub async fn wait_for_shutdown() -> io::Result<&'static str> {
+    use tokio::signal::unix::{
+        signal,
+        SignalKind,
+    };
+
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+
+    Ok(tokio::select! {
+        _ = interrupt.recv() => "SIGINT",
+        _ = terminate.recv() => "SIGTERM",
+    })
+}
+
+#[cfg(not(unix))]
+pub async fn wait_for_shutdown() -> io::Result<&'static str> {
+    tokio::signal::ctrl_c().await?;
+    Ok("Ctrl-C")
+}
@@ -0,0 +1,139 @@
+use std::{
+    env,
+    io,
+    time::Duration,
+};
+
+use fluxus::sockets::Sockets;
+
+/// First file descriptor passed by the socket activation
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+/// Takes sockets passed through the `LISTEN_FDS`. Sockets
+/// are matched to the endpoints by the
+/// `FileDescriptorName=` of the socket unit (e.g.
+/// `tcpflux`).
+///
+/// Must be called before any threads are spawned, since
+/// the environment is modified.
+#[cfg(unix)]
+pub fn take_activated_sockets(sockets: &Sockets) -> io::Result<()> {
+    use std::os::fd::{
+        BorrowedFd,
+        FromRawFd,
+        OwnedFd,
+    };
+
+    use nix::fcntl::{
+        fcntl,
+        FcntlArg,
+        FdFlag,
+    };
+
+    let pid = env::var("LISTEN_PID").ok();
+    if pid.and_then(|p| p.parse().ok()) != Some(std::process::id()) {
+        return Ok(());
+    }
+    let count: i32 = env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(0);
+    let names = env::var("LISTEN_FDNAMES").unwrap_or_default();
+
+    // Passed sockets must not leak into the child processes
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+
+    let mut activated = Vec::new();
+    let mut names = names.split(':');
+    for fd in LISTEN_FDS_START..LISTEN_FDS_START.saturating_add(count) {
+        // SAFETY: descriptors starting from the 3 are passed
+        // to this process and are not owned by anything else.
+        // Ownership is taken only once the descriptor is known
+        // to be open.
+        let borrowed = unsafe { BorrowedFd::borrow_raw(fd) };
+        fcntl(borrowed, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))?;
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let name = names.next().unwrap_or("unknown");
+        tracing::info!("Received {name} socket from the service manager");
+        activated.push((name.to_owned(), fd));
+    }
+
+    sockets.inherit_activated(activated);
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn take_activated_sockets(_sockets: &Sockets) -> io::Result<()> {
+    Ok(())
+}
+
+/// Sends the state to the service manager if it asked for
+/// notifications, e.g. `READY=1`
+#[cfg(unix)]
+pub fn notify(state: &str) {
+    use std::os::unix::net::UnixDatagram;
+
+    let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+
+    let send = || -> io::Result<()> {
+        let socket = UnixDatagram::unbound()?;
+        match path.as_encoded_bytes().strip_prefix(b"@") {
+            #[cfg(target_os = "linux")]
+            Some(name) => {
+                use std::os::{
+                    linux::net::SocketAddrExt,
+                    unix::net::SocketAddr,
+                };
+
+                let address = SocketAddr::from_abstract_name(name)?;
+                socket.send_to_addr(state.as_bytes(), &address)?;
+            }
+            _ => {
+                socket.send_to(state.as_bytes(), &path)?;
+            }
+        }
+
+        Ok(())
+    };
+
+    if let Err(e) = send() {
+        tracing::error!("Failed to notify the service manager: {e}");
+    }
+}
+
+#[cfg(not(unix))]
+pub fn notify(_state: &str) {}
+
+/// Pings the service manager watchdog, if it is enabled,
+/// twice per the configured interval
+pub async fn run_watchdog() {
+    let Some(interval) = watchdog_interval() else {
+        return std::future::pending().await;
+    };
+
+    let mut ticker = tokio::time::interval(interval / 2);
+    loop {
+        ticker.tick().await;
+        notify("WATCHDOG=1");
+    }
+}
+
+fn watchdog_interval() -> Option<Duration> {
+    if let Ok(pid) = env::var("WATCHDOG_PID") {
+        if pid.parse().ok() != Some(std::process::id()) {
+            return None;
+        }
+    }
+
+    let usec = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    match usec {
+        0 => None,
+        usec => Some(Duration::from_micros(usec)),
+    }
+}
@@ -0,0 +1,57 @@
+use std::{
+    future::Future,
+    path::Path,
+    sync::Arc,
+};
+
+use color_eyre::eyre;
+use fluxus::sockets::Sockets;
+
+/// Takes listening sockets over from the previous process
+/// if it serves handover on the `path`. Returned future
+/// resolves once the sockets are handed over to the next
+/// process.
+#[cfg(unix)]
+pub fn take_over(
+    path: Option<&Path>,
+    sockets: Arc<Sockets>,
+) -> eyre::Result<impl Future<Output = eyre::Result<()>>> {
+    use fluxus::handover;
+
+    let listener = match path {
+        Some(path) => {
+            let inherited = handover::receive(path)?;
+            if !inherited.is_empty() {
+                tracing::info!(
+                    "Took over {} listeners from the previous process",
+                    inherited.len()
+                );
+            }
+            sockets.inherit(inherited);
+
+            Some(handover::bind(path)?)
+        }
+        None => None,
+    };
+
+    Ok(async move {
+        let Some(listener) = listener else {
+            return std::future::pending().await;
+        };
+
+        handover::serve(listener, sockets).await?;
+        Ok(())
+    })
+}
+
+#[cfg(not(unix))]
+pub fn take_over(
+    path: Option<&Path>,
+    _sockets: Arc<Sockets>,
+) -> eyre::Result<impl Future<Output = eyre::Result<()>>> {
+    if path.is_some() {
+        eyre::bail!("socket handover is not supported on this platform");
+    }
+
+    Ok(std::future::pending())
+}
@@ -0,0 +1,113 @@
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: fluxctl [--config <path>] [--json] <command>
+
+Commands:
+  sessions              List sessions
+  proxies               List proxies
+  show <id>             Show the session
+  kick <id> [reason]    Disconnect the session
+  rights <id> <rights>  Replace rights of the session, e.g.
+                        \"CAN_CREATE_TCP_PROXY | CAN_PICK_TCP_PORT\"
+  close <port>          Close the TCP proxy
+  usage                 Show traffic of the users since the server start
+  reload                Reload config of the server
+
+Options:
+  --config <path>  Config of the server, the admin endpoint and token are
+                   taken from it. Defaults to the FLUXUS_CONFIG_PATH,
+                   /etc/fluxus.toml or ./fluxus.toml
+  --json           Print responses of the server as is";
+
+pub enum Command {
+    Sessions,
+    Proxies,
+    Show { id: u64 },
+    Kick { id: u64, reason: Option<String> },
+    Rights { id: u64, rights: String },
+    Close { port: u16 },
+    Usage,
+    Reload,
+    Help,
+}
+
+pub struct Args {
+    pub config: Option<PathBuf>,
+    pub json: bool,
+    pub command: Command,
+}
+
+impl Args {
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut config = None;
+        let mut json = false;
+        let mut positional = Vec::new();
+
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--json" => json = true,
+                "--config" => {
+                    let path = args.next().ok_or("--config requires the path")?;
+                    config = Some(PathBuf::from(path));
+                }
+                "-h" | "--help" => positional.insert(0, "help".to_owned()),
+                "--" => positional.extend(args.by_ref()),
+                flag if flag.starts_with("--") => {
+                    return Err(format!("unknown option {flag}"));
+                }
+                _ => positional.push(arg),
+            }
+        }
+
+        let command = parse_command(&positional)?;
+        Ok(Self {
+            config,
+            json,
+            command,
+        })
+    }
+}
+
+fn parse_command(args: &[String]) -> Result<Command, String> {
+    let Some((name, rest)) = args.split_first() else {
+        return Ok(Command::Help);
+    };
+
+    let command = match (name.as_str(), rest) {
+        ("help", _) => Command::Help,
+        ("sessions", []) => Command::Sessions,
+        ("proxies", []) => Command::Proxies,
+        ("show", [id]) => Command::Show { id: parse(id)? },
+        ("kick", [id, reason @ ..]) => Command::Kick {
+            id: parse(id)?,
+            reason: (!reason.is_empty()).then(|| reason.join(" ")),
+        },
+        ("rights", [id, rights @ ..]) if !rights.is_empty() => Command::Rights {
+            id: parse(id)?,
+            rights: rights.join(" "),
+        },
+        ("close", [port]) => Command::Close { port: parse(port)? },
+        ("usage", []) => Command::Usage,
+        ("reload", []) => Command::Reload,
+
+        (
+            "sessions" | "proxies" | "show" | "kick" | "rights" | "close" | "usage"
+            | "reload",
+            _,
+        ) => return Err(format!("invalid arguments of the {name}")),
+        _ => return Err(format!("unknown command {name}")),
+    };
+
+    Ok(command)
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|e| format!("invalid number {value}: {e}"))
+}
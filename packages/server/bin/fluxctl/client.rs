@@ -0,0 +1,120 @@
+use std::{
+    io::{
+        self,
+        Read,
+        Write,
+    },
+    net::TcpStream,
+    time::Duration,
+};
+
+use fluxus::config::server::ListenAddress;
+use serde::Deserialize;
+
+/// Listing waits for every session in turn, so be generous
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct Client {
+    address: ListenAddress,
+    token: Option<String>,
+}
+
+pub struct Reply {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+impl Client {
+    pub const fn new(address: ListenAddress, token: Option<String>) -> Self {
+        Self { address, token }
+    }
+
+    /// Performs the request, replies with non-2xx status
+    /// are turned into errors
+    pub fn request(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> Result<Reply, String> {
+        let body = body.map(ToString::to_string).unwrap_or_default();
+        let mut request = format!("{method} {path} HTTP/1.1\r\nHost: fluxus\r\n");
+        if let Some(ref token) = self.token {
+            request.push_str(&format!("Authorization: Bearer {token}\r\n"));
+        }
+        request.push_str(&format!(
+            "Content-Type: application/json\r\nContent-Length: {}\r\nConnection: \
+             close\r\n\r\n{body}",
+            body.len()
+        ));
+
+        let raw = self
+            .exchange(request.as_bytes())
+            .map_err(|e| format!("failed to reach {}: {e}", self.address))?;
+        let reply = parse_reply(&raw)?;
+        if !(200..300).contains(&reply.status) {
+            let message = serde_json::from_slice::<ErrorBody>(&reply.body)
+                .map(|e| e.error)
+                .unwrap_or_else(|_| String::from_utf8_lossy(&reply.body).into());
+            return Err(format!("server replied with {}: {message}", reply.status));
+        }
+
+        Ok(reply)
+    }
+
+    fn exchange(&self, request: &[u8]) -> io::Result<Vec<u8>> {
+        match self.address {
+            ListenAddress::Tcp(address) => {
+                let stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                exchange_over(stream, request)
+            }
+
+            #[cfg(unix)]
+            ListenAddress::Unix(ref path) => {
+                let stream = std::os::unix::net::UnixStream::connect(path)?;
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                exchange_over(stream, request)
+            }
+
+            #[cfg(not(unix))]
+            ListenAddress::Unix(..) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "unix sockets are not supported on this platform",
+            )),
+        }
+    }
+}
+
+fn exchange_over(
+    mut stream: impl Read + Write,
+    request: &[u8],
+) -> io::Result<Vec<u8>> {
+    stream.write_all(request)?;
+    stream.flush()?;
+
+    // Server closes the connection after the reply
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw)?;
+    Ok(raw)
+}
+
+fn parse_reply(raw: &[u8]) -> Result<Reply, String> {
+    let mut headers = [httparse::EMPTY_HEADER; 16];
+    let mut response = httparse::Response::new(&mut headers);
+    match response.parse(raw) {
+        Ok(httparse::Status::Complete(head_size)) => Ok(Reply {
+            status: response.code.unwrap_or_default(),
+            body: raw[head_size..].to_vec(),
+        }),
+        Ok(httparse::Status::Partial) => {
+            Err("server closed the connection too early".to_owned())
+        }
+        Err(e) => Err(format!("malformed reply: {e}")),
+    }
+}
@@ -0,0 +1,276 @@
+//! Operator CLI for the admin endpoint of the
+//! `flux-endpoint`
+
+use std::{
+    env,
+    io::IsTerminal,
+    path::{
+        Path,
+        PathBuf,
+    },
+    process::ExitCode,
+};
+
+use args::{
+    Args,
+    Command,
+    USAGE,
+};
+use client::Client;
+use fluxus::{
+    accounting::UserUsage,
+    config::root::Config,
+    protocols::tcp_flux::sessions::SessionInfo,
+};
+use owo_colors::OwoColorize;
+use serde::Deserialize;
+use serde_json::json;
+use table::{
+    bytes,
+    Table,
+};
+
+#[derive(Deserialize)]
+struct Closed {
+    closed: u64,
+}
+
+#[derive(Deserialize)]
+struct Reloaded {
+    restart_required: Vec<String>,
+}
+
+struct Output {
+    json: bool,
+    colored: bool,
+}
+
+fn load_config(path: Option<&Path>) -> Result<(PathBuf, Config), String> {
+    let candidates = match path {
+        Some(path) => vec![path.to_path_buf()],
+        None => match env::var_os("FLUXUS_CONFIG_PATH") {
+            Some(path) => vec![PathBuf::from(path)],
+            None => vec![
+                PathBuf::from("/etc/fluxus.toml"),
+                PathBuf::from("./fluxus.toml"),
+            ],
+        },
+    };
+
+    let mut last_error = String::new();
+    for path in candidates {
+        match Config::try_load(&path) {
+            Ok(config) => return Ok((path, config)),
+            Err(e) => last_error = format!("failed to load {}: {e}", path.display()),
+        }
+    }
+
+    Err(last_error)
+}
+
+fn session_table(sessions: &[SessionInfo]) -> Table {
+    let mut table = Table::new(vec![
+        "ID", "NAME", "ADDRESS", "RIGHTS", "PROXIES", "FLOWS", "RECEIVED", "SENT",
+    ]);
+    for session in sessions {
+        let mut name = session
+            .name
+            .clone()
+            .unwrap_or_else(|| "-".to_owned());
+        if session.draining {
+            name.push_str(" (draining)");
+        }
+
+        table.push(vec![
+            session.id.to_string(),
+            name,
+            session.address.clone(),
+            match session.rights.to_string() {
+                rights if rights.is_empty() => "-".to_owned(),
+                rights => rights,
+            },
+            match session.proxies.as_slice() {
+                [] => "-".to_owned(),
+                proxies => proxies
+                    .iter()
+                    .map(|p| format!("{}:{}", p.kind, p.port))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            },
+            session.stats.flows.to_string(),
+            bytes(session.stats.bytes_received),
+            bytes(session.stats.bytes_sent),
+        ]);
+    }
+
+    table
+}
+
+fn proxy_table(sessions: &[SessionInfo]) -> Table {
+    let mut table = Table::new(vec![
+        "KIND",
+        "PORT",
+        "SESSION",
+        "NAME",
+        "PENDING",
+        "FLOWS",
+        "CONNECTIONS",
+        "RECEIVED",
+        "SENT",
+    ]);
+    let mut proxies: Vec<_> = sessions
+        .iter()
+        .flat_map(|s| s.proxies.iter().map(move |p| (s, p)))
+        .collect();
+    proxies.sort_unstable_by_key(|(_, proxy)| proxy.port);
+
+    for (session, proxy) in proxies {
+        table.push(vec![
+            proxy.kind.clone(),
+            proxy.port.to_string(),
+            session.id.to_string(),
+            session
+                .name
+                .clone()
+                .unwrap_or_else(|| "-".to_owned()),
+            proxy.pending.to_string(),
+            proxy.flows.to_string(),
+            proxy.traffic.connections.to_string(),
+            bytes(proxy.traffic.bytes_received),
+            bytes(proxy.traffic.bytes_sent),
+        ]);
+    }
+
+    table
+}
+
+fn usage_table(users: &[UserUsage]) -> Table {
+    let mut table = Table::new(vec!["USER", "CONNECTIONS", "RECEIVED", "SENT"]);
+    for user in users {
+        table.push(vec![
+            user.user.clone(),
+            user.traffic.connections.to_string(),
+            bytes(user.traffic.bytes_received),
+            bytes(user.traffic.bytes_sent),
+        ]);
+    }
+
+    table
+}
+
+fn parse<'de, T: Deserialize<'de>>(body: &'de [u8]) -> Result<T, String> {
+    serde_json::from_slice(body).map_err(|e| format!("unexpected reply: {e}"))
+}
+
+fn run(client: &Client, command: Command, output: &Output) -> Result<(), String> {
+    let (method, path, body) = match command {
+        Command::Sessions | Command::Proxies => {
+            ("GET", "/sessions".to_owned(), None)
+        }
+        Command::Show { id } => ("GET", format!("/sessions/{id}"), None),
+        Command::Kick { id, ref reason } => (
+            "POST",
+            format!("/sessions/{id}/disconnect"),
+            Some(json!({ "reason": reason })),
+        ),
+        Command::Rights { id, ref rights } => (
+            "PUT",
+            format!("/sessions/{id}/rights"),
+            Some(json!({ "rights": rights })),
+        ),
+        Command::Close { port } => ("DELETE", format!("/proxies/tcp/{port}"), None),
+        Command::Usage => ("GET", "/usage".to_owned(), None),
+        Command::Reload => ("POST", "/reload".to_owned(), None),
+        Command::Help => {
+            println!("{USAGE}");
+            return Ok(());
+        }
+    };
+
+    let reply = client.request(method, &path, body.as_ref())?;
+    if output.json {
+        println!("{}", String::from_utf8_lossy(&reply.body));
+        return Ok(());
+    }
+
+    match command {
+        Command::Sessions => session_table(&parse::<Vec<SessionInfo>>(&reply.body)?)
+            .print(output.colored),
+        Command::Proxies => proxy_table(&parse::<Vec<SessionInfo>>(&reply.body)?)
+            .print(output.colored),
+        Command::Show { .. } | Command::Rights { .. } => {
+            let session: SessionInfo = parse(&reply.body)?;
+            session_table(&[session]).print(output.colored);
+        }
+        Command::Usage => {
+            usage_table(&parse::<Vec<UserUsage>>(&reply.body)?).print(output.colored)
+        }
+        Command::Kick { id, .. } => {
+            println!("Session {id} was disconnected");
+        }
+        Command::Close { port } => {
+            let closed: Closed = parse(&reply.body)?;
+            println!(
+                "Proxy on the port {port} was closed (session {})",
+                closed.closed
+            );
+        }
+        Command::Reload => {
+            let reloaded: Reloaded = parse(&reply.body)?;
+            println!("Config reloaded");
+            for section in reloaded.restart_required {
+                let warning = format!(
+                    "{section} was changed, restart is required to apply it"
+                );
+                if output.colored {
+                    println!("{}", warning.yellow());
+                } else {
+                    println!("{warning}");
+                }
+            }
+        }
+        Command::Help => unreachable!(),
+    }
+
+    Ok(())
+}
+
+fn try_main() -> Result<(), String> {
+    let args =
+        Args::parse(env::args().skip(1)).map_err(|e| format!("{e}\n\n{USAGE}"))?;
+    let output = Output {
+        json: args.json,
+        colored: std::io::stdout().is_terminal()
+            && env::var_os("NO_COLOR").is_none(),
+    };
+    if let Command::Help = args.command {
+        println!("{USAGE}");
+        return Ok(());
+    }
+
+    let (path, config) = load_config(args.config.as_deref())?;
+    let admin = config.admin.ok_or_else(|| {
+        format!("admin endpoint is not configured in the {}", path.display())
+    })?;
+    let client = Client::new(admin.listen, admin.token);
+
+    run(&client, args.command, &output)
+}
+
+fn main() -> ExitCode {
+    match try_main() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if std::io::stderr().is_terminal() {
+                eprintln!("{}: {e}", "error".red().bold());
+            } else {
+                eprintln!("error: {e}");
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+mod args;
+mod client;
+mod table;
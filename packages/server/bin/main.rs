@@ -1,36 +1,228 @@
-use std::sync::Arc;
+use std::{
+    future::Future,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 
 use boot::{
+    reload::run_reloader,
     runner::run_fut,
     setup::{
         configuration::load_config,
         environment::Environment,
-        logging::install_tracing,
+        logging::{
+            install_tracing,
+            LogLevelHandle,
+        },
+        privileges::drop_privileges,
         runtime::create_runtime,
     },
+    signals::wait_for_shutdown,
+    systemd::{
+        notify,
+        run_watchdog,
+        take_activated_sockets,
+    },
+    upgrade::take_over,
 };
 use color_eyre::eyre;
 use fluxus::{
-    config::root::Config,
+    accounting::{
+        quotas::Quotas,
+        records::UsageLog,
+        Usage,
+    },
+    config::{
+        handle::ConfigHandle,
+        reload::Reloader,
+        root::Config,
+    },
+    protocols::tcp_flux::{
+        events::master::MasterEvent,
+        sessions::Sessions,
+    },
     proxies::queues::Queues,
+    reservations::Reservations,
+    shutdown::Shutdown,
+    sockets::Sockets,
 };
+use tokio::sync::oneshot;
+
+/// Everything set up before the runtime is started
+struct Prepared {
+    config_path: PathBuf,
+    config: Config,
+    log_level: LogLevelHandle,
+    sockets: Arc<Sockets>,
+
+    #[cfg(feature = "tcpflux")]
+    tcp_flux: fluxus::protocols::tcp_flux::Endpoints,
+}
 
-async fn entrypoint(config: Config) -> eyre::Result<()> {
+async fn entrypoint(
+    prepared: Prepared,
+    handover: impl Future<Output = eyre::Result<()>>,
+) -> eyre::Result<()> {
     use fluxus::protocols as prot;
 
-    let config = Arc::new(config);
+    let Prepared {
+        config_path,
+        config,
+        log_level,
+        sockets,
+        #[cfg(feature = "tcpflux")]
+        tcp_flux,
+    } = prepared;
+    let config = ConfigHandle::new(config);
     let queues = Queues::default();
+    let sessions = Sessions::default();
+    let usage = Usage::default();
+    let usage_log = UsageLog::new(config.clone(), usage.clone());
+    let quotas = Quotas::load(config.clone(), usage.clone())?;
+    let reservations = Reservations::load(config.clone())?;
+
+    #[cfg_attr(not(feature = "admin"), allow(unused_variables))]
+    let (reloader, reload_requests) = Reloader::new();
+    let reloaded_sessions = sessions.clone();
+    run_fut(
+        "config reloader",
+        run_reloader(
+            config_path,
+            config.clone(),
+            log_level,
+            reload_requests,
+            move || reloaded_sessions.broadcast(|| MasterEvent::ConfigReloaded),
+        ),
+    );
+
+    let shutdown = Shutdown::default();
+    #[cfg(feature = "tcpflux")]
+    let (tcpflux_ready, tcpflux_started) = oneshot::channel();
     let futures = [
         #[cfg(feature = "tcpflux")]
         run_fut(
             "tcpflux",
-            prot::tcp_flux::run(queues.clone(), config.clone()),
+            prot::tcp_flux::run(
+                tcp_flux,
+                prot::tcp_flux::Shared::new(
+                    config.clone(),
+                    queues.clone(),
+                    sessions.clone(),
+                    usage.clone(),
+                    Arc::clone(&quotas),
+                    Arc::clone(&reservations),
+                    shutdown.clone(),
+                ),
+                Arc::clone(&sockets),
+                tcpflux_ready,
+            ),
+        ),
+        #[cfg(feature = "admin")]
+        run_fut(
+            "admin",
+            fluxus::admin::run(
+                config.clone(),
+                fluxus::admin::api::Context {
+                    sessions: sessions.clone(),
+                    usage: usage.clone(),
+                    reloader,
+                },
+                Arc::clone(&sockets),
+                shutdown.clone(),
+            ),
+        ),
+        #[cfg(feature = "metrics")]
+        run_fut(
+            "metrics",
+            fluxus::metrics::endpoint::run(
+                config.clone(),
+                sessions.clone(),
+                queues.clone(),
+                Arc::clone(&sockets),
+                shutdown.clone(),
+            ),
+        ),
+        run_fut(
+            "reservations",
+            Arc::clone(&reservations).run(shutdown.clone()),
+        ),
+        run_fut("usage log", Arc::clone(&usage_log).run(shutdown.clone())),
+        run_fut(
+            "quotas",
+            Arc::clone(&quotas).run(shutdown.clone(), {
+                let sessions = sessions.clone();
+                move |user| {
+                    let user: Arc<str> = Arc::from(user);
+                    sessions.broadcast(|| MasterEvent::QuotaExceeded {
+                        user: Arc::clone(&user),
+                    });
+                }
+            }),
         ),
     ];
 
-    futures_util::future::join_all(futures).await;
+    let started: Vec<oneshot::Receiver<()>> = vec![
+        #[cfg(feature = "tcpflux")]
+        tcpflux_started,
+    ];
+    tokio::spawn(async move {
+        // Endpoint that failed to start is reported by itself
+        let results = futures_util::future::join_all(started).await;
+        if results.iter().all(Result::is_ok) {
+            notify("READY=1");
+        }
+    });
+    tokio::spawn(run_watchdog());
+
+    tokio::select! {
+        _ = futures_util::future::join_all(futures) => {
+            return Ok(());
+        }
+
+        signal = wait_for_shutdown() => {
+            tracing::info!("Received {}, shutting down", signal?);
+        }
+
+        handed_over = handover => {
+            handed_over?;
+            tracing::info!("Listeners were handed over, shutting down");
+        }
+    }
+
+    notify("STOPPING=1");
+
+    // Listeners stop accepting and masters are notified, give
+    // the active flows some time to finish
+    shutdown.trigger();
+    let drain_period = Duration::from_secs(config.get().server.drain_period);
+    if tokio::time::timeout(drain_period, shutdown.drained())
+        .await
+        .is_err()
+    {
+        tracing::info!(
+            "Drain period elapsed, dropping {} active flows",
+            shutdown.active_flows()
+        );
+    }
+
+    if let Err(e) = usage_log.flush().await {
+        tracing::error!("Failed to write usage records: {e}");
+    }
+    if let Err(e) = quotas.flush().await {
+        tracing::error!("Failed to persist quotas: {e}");
+    }
+    if let Err(e) = reservations.flush().await {
+        tracing::error!("Failed to persist reservations: {e}");
+    }
+
+    // Otherwise it belongs to the next process now
+    if let Some(ref path) = config.get().server.handover_socket {
+        if !sockets.is_handed_over() {
+            _ = std::fs::remove_file(path);
+        }
+    }
 
-    _ = config;
     Ok(())
 }
 
@@ -38,12 +230,36 @@ fn main() -> eyre::Result<()> {
     color_eyre::install()?;
 
     let env = Environment::try_parse()?;
-    let config = load_config(env.config_path.as_deref())?;
-    install_tracing(&config.logging)?;
+    let (config_path, config) = load_config(env.config_path.as_deref())?;
+    let log_level = install_tracing(&config.logging)?;
+
+    // Listeners are bound before dropping privileges, and
+    // capabilities can be retained only by the single thread
+    let sockets = Arc::new(Sockets::default());
+    take_activated_sockets(&sockets)?;
+    let handover = take_over(
+        config.server.handover_socket.as_deref(),
+        Arc::clone(&sockets),
+    )?;
+    #[cfg(feature = "tcpflux")]
+    let tcp_flux = fluxus::protocols::tcp_flux::prepare(&config, &sockets)?;
+    #[cfg(feature = "admin")]
+    fluxus::admin::prepare(&config, &sockets)?;
+    #[cfg(feature = "metrics")]
+    fluxus::metrics::endpoint::prepare(&config, &sockets)?;
+    drop_privileges(&config.runtime)?;
 
     let rt = create_runtime(config.runtime.threads)?;
+    let prepared = Prepared {
+        config_path,
+        config,
+        log_level,
+        sockets,
+        #[cfg(feature = "tcpflux")]
+        tcp_flux,
+    };
 
-    rt.block_on(entrypoint(config))
+    rt.block_on(entrypoint(prepared, handover))
 }
 
 mod boot;
@@ -0,0 +1,161 @@
+//! Traffic of the proxies and users, see the
+//! [`records::UsageLog`] for the billing records and
+//! [`quotas::Quotas`] for the limits on it
+
+use std::{
+    ops,
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+        Arc,
+    },
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use dashmap::DashMap;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+pub mod quotas;
+pub mod records;
+
+/// Connections and bytes forwarded by the proxies
+#[derive(Default)]
+pub struct Traffic {
+    connections: AtomicU64,
+    received: AtomicU64,
+    sent: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrafficInfo {
+    pub connections: u64,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+}
+
+/// Totals of the authenticated users since the start,
+/// keyed by the user name
+#[derive(Clone, Default)]
+pub struct Usage {
+    users: Arc<DashMap<String, Arc<Traffic>>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserUsage {
+    pub user: String,
+
+    #[serde(flatten)]
+    pub traffic: TrafficInfo,
+}
+
+impl Traffic {
+    pub fn add_connection(&self) {
+        self.connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Bytes received from the public peers
+    pub fn add_received(&self, bytes: usize) {
+        self.received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Bytes sent to the public peers
+    pub fn add_sent(&self, bytes: usize) {
+        self.sent
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> TrafficInfo {
+        TrafficInfo {
+            connections: self.connections.load(Ordering::Relaxed),
+            bytes_received: self.received.load(Ordering::Relaxed),
+            bytes_sent: self.sent.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl TrafficInfo {
+    /// Traffic since the `earlier` snapshot
+    pub const fn since(self, earlier: Self) -> Self {
+        Self {
+            connections: self
+                .connections
+                .saturating_sub(earlier.connections),
+            bytes_received: self
+                .bytes_received
+                .saturating_sub(earlier.bytes_received),
+            bytes_sent: self.bytes_sent.saturating_sub(earlier.bytes_sent),
+        }
+    }
+
+    /// Bytes forwarded in both directions
+    pub const fn bytes(&self) -> u64 {
+        self.bytes_received
+            .saturating_add(self.bytes_sent)
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.connections == 0 && self.bytes_received == 0 && self.bytes_sent == 0
+    }
+}
+
+impl ops::Add for TrafficInfo {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            connections: self.connections + rhs.connections,
+            bytes_received: self.bytes_received + rhs.bytes_received,
+            bytes_sent: self.bytes_sent + rhs.bytes_sent,
+        }
+    }
+}
+
+impl Usage {
+    /// Counters of the `user`, created on the first use
+    pub fn user(&self, name: &str) -> Arc<Traffic> {
+        if let Some(traffic) = self.users.get(name) {
+            return Arc::clone(&traffic);
+        }
+
+        Arc::clone(&self.users.entry(name.to_owned()).or_default())
+    }
+
+    /// Counters of the `user`, zeroed if there is no
+    /// traffic yet
+    pub fn of(&self, name: &str) -> TrafficInfo {
+        self.users
+            .get(name)
+            .map_or_else(TrafficInfo::default, |traffic| traffic.snapshot())
+    }
+
+    /// Snapshot of all users, sorted by the name
+    pub fn users(&self) -> Vec<UserUsage> {
+        let mut users: Vec<_> = self
+            .users
+            .iter()
+            .map(|entry| UserUsage {
+                user: entry.key().clone(),
+                traffic: entry.value().snapshot(),
+            })
+            .collect();
+        users.sort_unstable_by(|lhs, rhs| lhs.user.cmp(&rhs.user));
+
+        users
+    }
+}
+
+/// Seconds since the epoch
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
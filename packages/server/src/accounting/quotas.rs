@@ -0,0 +1,383 @@
+use std::{
+    collections::{
+        BTreeMap,
+        HashMap,
+        HashSet,
+    },
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::{
+        Arc,
+        Mutex,
+        MutexGuard,
+    },
+    time::Duration,
+};
+
+use color_eyre::eyre;
+
+use super::{
+    unix_now,
+    Usage,
+};
+use crate::{
+    config::handle::ConfigHandle,
+    shutdown::Shutdown,
+};
+
+/// How often users are checked against their quotas and
+/// the usage is persisted
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct Database {
+    /// Start of the current period, seconds since the epoch
+    #[serde(default)]
+    period_start: u64,
+
+    #[serde(default)]
+    used: BTreeMap<String, u64>,
+}
+
+struct Period {
+    started: u64,
+
+    // Used before the restart, read from the database
+    carried: BTreeMap<String, u64>,
+    // Totals of the users when the period started
+    offsets: HashMap<String, u64>,
+    // Users already reported by the `check`
+    exceeded: HashSet<String>,
+    persisted: BTreeMap<String, u64>,
+}
+
+/// Data quota of the user in the current period
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaStatus {
+    pub used: u64,
+    pub limit: u64,
+
+    /// Seconds since the epoch
+    pub resets_at: u64,
+}
+
+/// Quota of the single user, checked by their flows before
+/// every forwarded chunk
+#[derive(Clone)]
+pub struct UserQuota {
+    quotas: Arc<Quotas>,
+    user: String,
+}
+
+impl UserQuota {
+    pub fn is_exceeded(&self) -> bool {
+        self.quotas.is_exceeded(&self.user)
+    }
+}
+
+/// Bytes forwarded by the users over the configured period,
+/// both directions are counted
+pub struct Quotas {
+    config: ConfigHandle,
+    usage: Usage,
+    database: Option<PathBuf>,
+    period: Mutex<Period>,
+}
+
+impl Quotas {
+    /// Reads usage of the current period from the database,
+    /// missing database is treated as empty
+    pub fn load(config: ConfigHandle, usage: Usage) -> eyre::Result<Arc<Self>> {
+        let database = config
+            .get()
+            .quotas
+            .as_ref()
+            .and_then(|q| q.database.clone());
+        let stored = match database {
+            Some(ref path) => read_database(path)?,
+            None => Database::default(),
+        };
+        let started = match stored.period_start {
+            0 => unix_now(),
+            started => started,
+        };
+
+        Ok(Arc::new(Self {
+            config,
+            usage,
+            database,
+            period: Mutex::new(Period {
+                started,
+                persisted: stored.used.clone(),
+                carried: stored.used,
+                offsets: HashMap::new(),
+                exceeded: HashSet::new(),
+            }),
+        }))
+    }
+
+    /// Quota of the `user`, [`None`] if the user has none
+    pub fn status(&self, user: &str) -> Option<QuotaStatus> {
+        let config = self.config.get();
+        let quotas = config.quotas.as_ref()?;
+        let limit = quotas.limit_of(user)?;
+
+        let period = self.period(quotas.period_secs());
+        Some(QuotaStatus {
+            used: self.used(&period, user),
+            limit,
+            resets_at: period.started + quotas.period_secs(),
+        })
+    }
+
+    pub fn is_exceeded(&self, user: &str) -> bool {
+        self.status(user)
+            .is_some_and(|status| status.used >= status.limit)
+    }
+
+    /// Quota of the `user` for their flows
+    pub fn of(self: &Arc<Self>, user: &str) -> UserQuota {
+        UserQuota {
+            quotas: Arc::clone(self),
+            user: user.to_owned(),
+        }
+    }
+
+    /// Checks users every [`CHECK_INTERVAL`] until the
+    /// shutdown, calling `on_exceeded` once per period for
+    /// every user that ran out of the quota
+    pub async fn run(
+        self: Arc<Self>,
+        shutdown: Shutdown,
+        on_exceeded: impl Fn(&str),
+    ) -> eyre::Result<()> {
+        loop {
+            tokio::select! {
+                () = shutdown.triggered() => {
+                    return Ok(());
+                }
+
+                () = tokio::time::sleep(CHECK_INTERVAL) => {}
+            }
+
+            for user in self.newly_exceeded() {
+                tracing::info!("{user} exceeded the data quota");
+                on_exceeded(&user);
+            }
+            if let Err(e) = self.flush().await {
+                tracing::error!("Failed to persist quotas: {e}");
+            }
+        }
+    }
+
+    /// Persists usage of the current period. No-op if the
+    /// database is not configured or nothing changed
+    pub async fn flush(self: &Arc<Self>) -> io::Result<()> {
+        if self.database.is_none() {
+            return Ok(());
+        }
+
+        let this = Arc::clone(self);
+        tokio::task::spawn_blocking(move || this.write())
+            .await
+            .map_err(io::Error::other)?
+    }
+
+    fn newly_exceeded(&self) -> Vec<String> {
+        let config = self.config.get();
+        let Some(quotas) = config.quotas.as_ref() else {
+            return Vec::new();
+        };
+
+        let mut period = self.period(quotas.period_secs());
+        let mut exceeded = Vec::new();
+        for user in self.usage.users() {
+            let Some(limit) = quotas.limit_of(&user.user) else {
+                continue;
+            };
+            if self.used(&period, &user.user) >= limit
+                && period.exceeded.insert(user.user.clone())
+            {
+                exceeded.push(user.user);
+            }
+        }
+
+        exceeded
+    }
+
+    fn write(&self) -> io::Result<()> {
+        let Some(ref path) = self.database else {
+            return Ok(());
+        };
+        let period_secs = self
+            .config
+            .get()
+            .quotas
+            .as_ref()
+            .map_or(u64::MAX, |q| q.period_secs());
+
+        let mut period = self.period(period_secs);
+        let mut used = period.carried.clone();
+        for user in self.usage.users() {
+            let bytes = self.used(&period, &user.user);
+            if bytes != 0 {
+                used.insert(user.user, bytes);
+            }
+        }
+        if used == period.persisted {
+            return Ok(());
+        }
+
+        let database = Database {
+            period_start: period.started,
+            used,
+        };
+        write_database(path, &database)?;
+        period.persisted = database.used;
+
+        Ok(())
+    }
+
+    fn used(&self, period: &Period, user: &str) -> u64 {
+        let total = self.usage.of(user).bytes();
+        let offset = period.offsets.get(user).copied().unwrap_or(0);
+        let carried = period.carried.get(user).copied().unwrap_or(0);
+
+        carried + total.saturating_sub(offset)
+    }
+
+    /// Locks the current period, starting the new one if it
+    /// has ended
+    fn period(&self, period_secs: u64) -> MutexGuard<'_, Period> {
+        let mut period = self
+            .period
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let now = unix_now();
+        let elapsed = now.saturating_sub(period.started);
+        if elapsed < period_secs {
+            return period;
+        }
+
+        // Periods stay aligned to the first one
+        period.started += elapsed - elapsed % period_secs;
+        period.carried.clear();
+        period.exceeded.clear();
+        period.offsets = self
+            .usage
+            .users()
+            .into_iter()
+            .map(|user| (user.user, user.traffic.bytes()))
+            .collect();
+
+        period
+    }
+}
+
+fn read_database(path: &Path) -> eyre::Result<Database> {
+    match fs::read_to_string(path) {
+        Ok(contents) => toml::from_str(&contents).map_err(From::from),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Database::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Writes database to the temporary file first, so it's
+/// never left half-written
+fn write_database(path: &Path, database: &Database) -> io::Result<()> {
+    let contents = toml::to_string(database)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let temporary = path.with_extension("tmp");
+
+    fs::write(&temporary, contents)?;
+    fs::rename(temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::root::Config;
+
+    const DAY: u64 = 24 * 60 * 60;
+    const CONFIG: &str = r#"
+        [server]
+        name = "fluxus"
+        protocols.tcp_flux.listen = "127.0.0.1:0"
+
+        [logging]
+        level = "info"
+
+        [security]
+
+        [quotas]
+        period = 1
+        default = 1000
+    "#;
+
+    fn quotas(usage: &Usage) -> Arc<Quotas> {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        Quotas::load(ConfigHandle::new(config), usage.clone()).unwrap()
+    }
+
+    /// Moves start of the current period `secs` back
+    fn rewind(quotas: &Quotas, secs: u64) -> u64 {
+        let mut period = quotas.period.lock().unwrap();
+        period.started -= secs;
+        period.started
+    }
+
+    #[test]
+    fn carried_usage_counts_towards_quota() {
+        let usage = Usage::default();
+        let quotas = quotas(&usage);
+        quotas
+            .period
+            .lock()
+            .unwrap()
+            .carried
+            .insert("bob".to_owned(), 600);
+
+        usage.user("bob").add_received(300);
+        assert_eq!(quotas.status("bob").unwrap().used, 900);
+        assert!(!quotas.is_exceeded("bob"));
+
+        usage.user("bob").add_sent(100);
+        assert!(quotas.is_exceeded("bob"));
+    }
+
+    #[test]
+    fn rollover_resets_usage() {
+        let usage = Usage::default();
+        let quotas = quotas(&usage);
+        quotas
+            .period
+            .lock()
+            .unwrap()
+            .carried
+            .insert("bob".to_owned(), 600);
+        usage.user("bob").add_received(500);
+        assert!(quotas.is_exceeded("bob"));
+
+        let started = rewind(&quotas, DAY);
+        let status = quotas.status("bob").unwrap();
+        assert_eq!(status.used, 0);
+        assert_eq!(status.resets_at, started + 2 * DAY);
+
+        // Traffic before the rollover is not counted again
+        usage.user("bob").add_sent(50);
+        assert_eq!(quotas.status("bob").unwrap().used, 50);
+    }
+
+    #[test]
+    fn periods_stay_aligned() {
+        let usage = Usage::default();
+        let quotas = quotas(&usage);
+        let started = rewind(&quotas, 3 * DAY + 100);
+
+        assert_eq!(quotas.period(DAY).started, started + 3 * DAY);
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::{
+        self,
+        Write,
+    },
+    path::PathBuf,
+    sync::{
+        Arc,
+        Mutex,
+    },
+    time::Duration,
+};
+
+use color_eyre::eyre;
+use serde::Serialize;
+
+use super::{
+    unix_now,
+    TrafficInfo,
+    Usage,
+};
+use crate::{
+    config::handle::ConfigHandle,
+    shutdown::Shutdown,
+};
+
+/// Log might be configured by the reload, so it is
+/// checked from time to time
+const IDLE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Line of the usage log: traffic of the user over the
+/// period, timestamps are in seconds since the epoch
+#[derive(Serialize)]
+struct UsageRecord<'a> {
+    period_start: u64,
+    period_end: u64,
+    user: &'a str,
+
+    #[serde(flatten)]
+    traffic: TrafficInfo,
+}
+
+struct Reported {
+    at: u64,
+    users: HashMap<String, TrafficInfo>,
+}
+
+/// Appends usage records to the configured file as JSON
+/// lines. Every record covers traffic since the previous
+/// one, users without traffic are skipped.
+pub struct UsageLog {
+    config: ConfigHandle,
+    usage: Usage,
+    reported: Mutex<Reported>,
+}
+
+impl UsageLog {
+    pub fn new(config: ConfigHandle, usage: Usage) -> Arc<Self> {
+        Arc::new(Self {
+            config,
+            usage,
+            reported: Mutex::new(Reported {
+                at: unix_now(),
+                users: HashMap::new(),
+            }),
+        })
+    }
+
+    /// Writes records every configured interval until the
+    /// shutdown. Final records are written by the
+    /// [`Self::flush`] once the flows are drained
+    pub async fn run(self: Arc<Self>, shutdown: Shutdown) -> eyre::Result<()> {
+        loop {
+            let interval = self
+                .config
+                .get()
+                .accounting
+                .as_ref()
+                .map_or(IDLE_INTERVAL, |a| Duration::from_secs(a.interval));
+
+            tokio::select! {
+                () = shutdown.triggered() => {
+                    return Ok(());
+                }
+
+                () = tokio::time::sleep(interval) => {}
+            }
+
+            if let Err(e) = self.flush().await {
+                tracing::error!("Failed to write usage records: {e}");
+            }
+        }
+    }
+
+    /// Writes records of the traffic since the previous
+    /// ones. Nothing is written if the log is not
+    /// configured, the traffic is kept for the next time
+    pub async fn flush(self: &Arc<Self>) -> io::Result<()> {
+        let Some(path) = self
+            .config
+            .get()
+            .accounting
+            .as_ref()
+            .map(|a| a.usage_log.clone())
+        else {
+            return Ok(());
+        };
+
+        let this = Arc::clone(self);
+        tokio::task::spawn_blocking(move || this.write(path))
+            .await
+            .map_err(io::Error::other)?
+    }
+
+    fn write(&self, path: PathBuf) -> io::Result<()> {
+        let mut reported = self.reported.lock().unwrap();
+        let now = unix_now();
+        let users = self.usage.users();
+
+        let mut buf = Vec::new();
+        for user in &users {
+            let previous = reported
+                .users
+                .get(&user.user)
+                .copied()
+                .unwrap_or_default();
+            let traffic = user.traffic.since(previous);
+            if traffic.is_empty() {
+                continue;
+            }
+
+            serde_json::to_writer(
+                &mut buf,
+                &UsageRecord {
+                    period_start: reported.at,
+                    period_end: now,
+                    user: &user.user,
+                    traffic,
+                },
+            )?;
+            buf.push(b'\n');
+        }
+
+        if !buf.is_empty() {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)?
+                .write_all(&buf)?;
+        }
+
+        // Updated only once written, so the failed period is
+        // included into the next one
+        reported.at = now;
+        reported.users = users
+            .into_iter()
+            .map(|user| (user.user, user.traffic))
+            .collect();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,266 @@
+use std::time::Duration;
+
+use flux_common::Rights;
+use futures_util::future::join_all;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tokio::{
+    sync::oneshot,
+    time::timeout,
+};
+
+use crate::{
+    accounting::Usage,
+    config::reload::Reloader,
+    local_http::{
+        Request,
+        Response,
+    },
+    protocols::tcp_flux::{
+        events::master::{
+            AdminCommand,
+            MasterEvent,
+        },
+        sessions::{
+            SessionId,
+            SessionInfo,
+            Sessions,
+        },
+    },
+};
+
+/// Time given to the session to answer, it might be busy
+/// writing to the slow client
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// State the requests are served from
+#[derive(Clone)]
+pub struct Context {
+    pub sessions: Sessions,
+    pub usage: Usage,
+    pub reloader: Reloader,
+}
+
+pub struct ApiError {
+    status: u16,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: &'a str,
+}
+
+#[derive(Deserialize, Default)]
+struct DisconnectBody {
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RightsBody {
+    rights: Rights,
+}
+
+#[derive(Serialize)]
+struct ClosedBody {
+    closed: SessionId,
+}
+
+#[derive(Serialize)]
+struct ReloadedBody {
+    restart_required: Vec<&'static str>,
+}
+
+impl ApiError {
+    pub fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn not_found(what: impl Into<String>) -> Self {
+        Self::new(404, what)
+    }
+
+    fn bad_request(error: impl ToString) -> Self {
+        Self::new(400, error.to_string())
+    }
+}
+
+impl From<ApiError> for Response {
+    fn from(error: ApiError) -> Self {
+        json(
+            error.status,
+            &ErrorBody {
+                error: &error.message,
+            },
+        )
+    }
+}
+
+/// Routes the request:
+///
+/// - `GET /sessions`
+/// - `GET /sessions/{id}`
+/// - `POST /sessions/{id}/disconnect` with optional
+///   `{"reason": "..."}`
+/// - `PUT /sessions/{id}/rights` with `{"rights": "A | B"}`
+/// - `DELETE /proxies/tcp/{port}`
+/// - `GET /usage`
+/// - `POST /reload`
+pub async fn handle(
+    request: Request,
+    context: &Context,
+) -> Result<Response, ApiError> {
+    let Context {
+        ref sessions,
+        ref usage,
+        ref reloader,
+    } = *context;
+    let path = request.path.split('?').next().unwrap_or_default();
+    let segments: Vec<_> = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["sessions"]) => Ok(json(200, &list(sessions).await)),
+        ("GET", ["sessions", id]) => {
+            let id = parse_id(id)?;
+            Ok(json(200, &describe(sessions, id).await?))
+        }
+        ("POST", ["sessions", id, "disconnect"]) => {
+            let id = parse_id(id)?;
+            let body: DisconnectBody = if request.body.is_empty() {
+                DisconnectBody::default()
+            } else {
+                serde_json::from_slice(&request.body)
+                    .map_err(ApiError::bad_request)?
+            };
+            let reason = body
+                .reason
+                .unwrap_or_else(|| "no reason given".to_owned());
+
+            send(sessions, id, AdminCommand::Disconnect { reason })?;
+            Ok(json(200, &ClosedBody { closed: id }))
+        }
+        ("PUT", ["sessions", id, "rights"]) => {
+            let id = parse_id(id)?;
+            let body: RightsBody = serde_json::from_slice(&request.body)
+                .map_err(ApiError::bad_request)?;
+
+            let (reply, rx) = oneshot::channel();
+            send(
+                sessions,
+                id,
+                AdminCommand::SetRights {
+                    rights: body.rights,
+                    reply,
+                },
+            )?;
+            await_reply(rx).await?;
+
+            Ok(json(200, &describe(sessions, id).await?))
+        }
+        ("DELETE", ["proxies", "tcp", port]) => {
+            let port: u16 = port.parse().map_err(ApiError::bad_request)?;
+            close_proxy(sessions, port).await
+        }
+        ("GET", ["usage"]) => Ok(json(200, &usage.users())),
+        ("POST", ["reload"]) => match reloader.reload().await {
+            Some(Ok(restart_required)) => {
+                Ok(json(200, &ReloadedBody { restart_required }))
+            }
+            Some(Err(e)) => Err(ApiError::new(422, e)),
+            None => Err(ApiError::new(503, "config reloader is not running")),
+        },
+
+        (_, ["sessions" | "proxies" | "usage" | "reload", ..]) => {
+            Err(ApiError::new(405, "method not allowed"))
+        }
+        _ => Err(ApiError::not_found("no such endpoint")),
+    }
+}
+
+async fn list(sessions: &Sessions) -> Vec<SessionInfo> {
+    // All sessions are asked at once, so the slow ones do
+    // not add up
+    let replies = sessions.all().into_iter().filter_map(|(id, tx)| {
+        let (reply, rx) = oneshot::channel();
+        tx.send(MasterEvent::Admin(AdminCommand::Describe { reply }))
+            .ok()
+            .map(|()| async move { (id, await_reply(rx).await) })
+    });
+
+    let mut infos = Vec::with_capacity(sessions.len());
+    for (id, reply) in join_all(replies).await {
+        match reply {
+            Ok(info) => infos.push(info),
+            Err(e) => {
+                tracing::warn!("Session {id} did not describe itself: {}", e.message)
+            }
+        }
+    }
+
+    infos.sort_unstable_by_key(|info| info.id);
+    infos
+}
+
+async fn describe(
+    sessions: &Sessions,
+    id: SessionId,
+) -> Result<SessionInfo, ApiError> {
+    let (reply, rx) = oneshot::channel();
+    send(sessions, id, AdminCommand::Describe { reply })?;
+
+    await_reply(rx).await
+}
+
+async fn close_proxy(sessions: &Sessions, port: u16) -> Result<Response, ApiError> {
+    let no_proxy = || ApiError::not_found(format!("no proxy on the port {port}"));
+    let owner = sessions.owner_of(port).ok_or_else(no_proxy)?;
+
+    let (reply, rx) = oneshot::channel();
+    send(sessions, owner, AdminCommand::CloseProxy { port, reply })?;
+    if !await_reply(rx).await? {
+        return Err(no_proxy());
+    }
+
+    Ok(json(200, &ClosedBody { closed: owner }))
+}
+
+fn send(
+    sessions: &Sessions,
+    id: SessionId,
+    command: AdminCommand,
+) -> Result<(), ApiError> {
+    sessions
+        .get(id)
+        .and_then(|tx| tx.send(MasterEvent::Admin(command)).ok())
+        .ok_or_else(|| ApiError::not_found(format!("no session with id {id}")))
+}
+
+async fn await_reply<T>(rx: oneshot::Receiver<T>) -> Result<T, ApiError> {
+    match timeout(REPLY_TIMEOUT, rx).await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(..)) => Err(ApiError::not_found("session was closed")),
+        Err(..) => Err(ApiError::new(504, "session did not reply in time")),
+    }
+}
+
+fn parse_id(id: &str) -> Result<SessionId, ApiError> {
+    id.parse().map_err(ApiError::bad_request)
+}
+
+pub fn json(status: u16, body: &impl Serialize) -> Response {
+    Response {
+        status,
+        content_type: "application/json",
+        // Serialization of the plain structures can't fail
+        body: serde_json::to_vec(body).unwrap_or_default(),
+    }
+}
@@ -0,0 +1,98 @@
+//! Local administration endpoint: JSON over HTTP, see the
+//! [`api::handle`] for the list of routes
+
+use std::sync::Arc;
+
+use color_eyre::eyre;
+
+use crate::{
+    config::{
+        handle::ConfigHandle,
+        root::Config,
+    },
+    local_http::{
+        self,
+        Request,
+        Response,
+    },
+    shutdown::Shutdown,
+    sockets::Sockets,
+};
+
+pub mod api;
+
+/// Name under which listener is passed to the upgraded
+/// process
+const ADMIN_SOCKET: &str = "admin";
+
+/// Binds the admin listener if it is configured
+pub fn prepare(config: &Config, sockets: &Sockets) -> eyre::Result<()> {
+    let Some(ref admin) = config.admin else {
+        return Ok(());
+    };
+
+    let address = sockets.bind_listen_address(
+        ADMIN_SOCKET,
+        &admin.listen,
+        admin.socket_mode,
+    )?;
+    tracing::info!("Admin endpoint is listening on {address}");
+
+    Ok(())
+}
+
+/// Serves the admin endpoint until the shutdown
+pub async fn run(
+    config: ConfigHandle,
+    context: api::Context,
+    sockets: Arc<Sockets>,
+    shutdown: Shutdown,
+) -> eyre::Result<()> {
+    let snapshot = config.get();
+    let Some(ref admin) = snapshot.admin else {
+        return Ok(());
+    };
+
+    let handler = move |request| {
+        let config = config.clone();
+        let context = context.clone();
+        async move { handle_request(request, &config, &context).await }
+    };
+    local_http::run(ADMIN_SOCKET, &admin.listen, &sockets, shutdown, handler).await
+}
+
+async fn handle_request(
+    request: Request,
+    config: &ConfigHandle,
+    context: &api::Context,
+) -> Response {
+    if !authorized(&config.get(), request.authorization.as_deref()) {
+        return api::ApiError::new(401, "invalid token").into();
+    }
+
+    tracing::info!("Admin request: {} {}", request.method, request.path);
+    api::handle(request, context)
+        .await
+        .unwrap_or_else(Into::into)
+}
+
+fn authorized(config: &Config, authorization: Option<&str>) -> bool {
+    let Some(expected) = config
+        .admin
+        .as_ref()
+        .and_then(|a| a.token.as_ref())
+    else {
+        return true;
+    };
+    let Some(token) = authorization.and_then(|a| a.strip_prefix("Bearer ")) else {
+        return false;
+    };
+
+    // Compared in constant time
+    token.len() == expected.len()
+        && token
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
@@ -0,0 +1,16 @@
+use std::path::PathBuf;
+
+entity! {
+    struct AccountingConfig {
+        // Usage records are appended to this file as JSON lines
+        usage_log: PathBuf,
+
+        // Seconds between the records
+        #[serde(default = "default_interval")]
+        interval: u64,
+    }
+}
+
+const fn default_interval() -> u64 {
+    300
+}
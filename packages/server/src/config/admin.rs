@@ -0,0 +1,29 @@
+use super::server::ListenAddress;
+
+entity! {
+    // Local JSON-over-HTTP endpoint for the sessions management
+    #[derive(Clone)]
+    struct AdminConfig {
+        // `ip:port` or `unix:/path/to/socket`, keep it local
+        listen: ListenAddress,
+
+        // Permission bits of the unix socket file (e.g. `0o600`)
+        socket_mode: Option<u32>,
+
+        // Expected in the `Authorization: Bearer <token>` header
+        // if specified. Required unless the endpoint listens on
+        // the loopback or unix socket
+        token: Option<String>,
+    }
+}
+
+impl AdminConfig {
+    /// Whether the endpoint is reachable from the other
+    /// hosts
+    pub const fn is_exposed(&self) -> bool {
+        matches!(
+            self.listen,
+            ListenAddress::Tcp(address) if !address.ip().is_loopback()
+        )
+    }
+}
@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+use super::root::Config;
+
+/// Config that can be replaced while the server is running.
+/// Readers get a consistent snapshot, so take it once per
+/// operation instead of re-loading it for every field
+#[derive(Clone)]
+pub struct ConfigHandle {
+    inner: Arc<ArcSwap<Config>>,
+}
+
+impl ConfigHandle {
+    pub fn new(config: Config) -> Self {
+        Self {
+            inner: Arc::new(ArcSwap::from_pointee(config)),
+        }
+    }
+
+    /// Current snapshot of the config
+    pub fn get(&self) -> Arc<Config> {
+        self.inner.load_full()
+    }
+
+    /// Replaces config with the `config`, sections that
+    /// can't be applied without the restart are kept as
+    /// they were. Returns such sections if they were
+    /// changed. Not meant to be called concurrently,
+    /// the reloader is the only writer
+    pub fn replace(&self, mut config: Config) -> Vec<&'static str> {
+        let previous = self.get();
+        let restart_required = previous.carry_over_restart_required(&mut config);
+        self.inner.store(Arc::new(config));
+
+        restart_required
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+entity! {
+    #[derive(Default)]
+    struct LimitsConfig {
+        #[serde(flatten)]
+        default: Limits,
+
+        // Overrides of the defaults, keyed by the user name. Only
+        // the specified limits are overridden
+        #[serde(default)]
+        users: HashMap<String, Limits>,
+    }
+
+    // Unlimited if not set
+    #[derive(Default, Clone, Copy)]
+    struct Limits {
+        proxies_per_session: Option<usize>,
+        sessions_per_user: Option<usize>,
+
+        // Claimed and pending connections together
+        flows_per_proxy: Option<usize>,
+
+        // Accepted connections not claimed by the flow yet
+        pending_per_proxy: Option<usize>,
+    }
+}
+
+impl LimitsConfig {
+    /// Limits of the `user`, anonymous users get the
+    /// default ones
+    pub fn of_user(&self, user: Option<&str>) -> Limits {
+        let default = self.default;
+        let Some(limits) = user.and_then(|user| self.users.get(user)) else {
+            return default;
+        };
+
+        Limits {
+            proxies_per_session: limits
+                .proxies_per_session
+                .or(default.proxies_per_session),
+            sessions_per_user: limits
+                .sessions_per_user
+                .or(default.sessions_per_user),
+            flows_per_proxy: limits.flows_per_proxy.or(default.flows_per_proxy),
+            pending_per_proxy: limits
+                .pending_per_proxy
+                .or(default.pending_per_proxy),
+        }
+    }
+}
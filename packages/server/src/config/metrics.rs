@@ -0,0 +1,13 @@
+use super::server::ListenAddress;
+
+entity! {
+    // Prometheus metrics, served at the `/metrics`
+    #[derive(Clone)]
+    struct MetricsConfig {
+        // `ip:port` or `unix:/path/to/socket`
+        listen: ListenAddress,
+
+        // Permission bits of the unix socket file (e.g. `0o660`)
+        socket_mode: Option<u32>,
+    }
+}
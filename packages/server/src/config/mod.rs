@@ -12,7 +12,7 @@ macro_rules! entity {
         $($tail:tt)*
     ) => {
         $(#[$outer_meta])*
-        #[derive(Debug, serde::Serialize, serde::Deserialize)]
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
         pub struct $name {
             $(
                 $(#[$field_meta])*
@@ -25,7 +25,19 @@ macro_rules! entity {
     () => {};
 }
 
+pub mod accounting;
+#[cfg(feature = "admin")]
+pub mod admin;
+pub mod handle;
+pub mod limits;
 pub mod logging;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod proxies;
+pub mod quotas;
+pub mod rate_limits;
+pub mod reload;
+pub mod reservations;
 pub mod runtime;
 pub mod security;
 pub mod server;
@@ -0,0 +1,149 @@
+#[cfg(feature = "tcp")]
+use std::{
+    fmt,
+    net::{
+        IpAddr,
+        Ipv4Addr,
+    },
+};
+
+entity! {
+    #[derive(Default)]
+    struct ProxiesConfig {
+        #[cfg(feature = "tcp")]
+        #[serde(default)]
+        tcp: TcpProxiesConfig,
+    }
+
+    #[cfg(feature = "tcp")]
+    struct TcpProxiesConfig {
+        // Expect PROXY protocol header (v1 or v2) from the load
+        // balancer in front of the public listeners
+        #[serde(default)]
+        accept_proxy_protocol: bool,
+
+        // Same, but only for the proxies bound within these ranges,
+        // when just some of the ports are behind the load balancer
+        #[serde(default)]
+        proxy_protocol_ports: Vec<PortRange>,
+
+        // Seconds accepted connection waits for the client to
+        // claim it, before it's closed
+        #[serde(default = "default_claim_timeout")]
+        claim_timeout: u64,
+
+        // Interface for the public listeners, `::` together with
+        // `dual_stack` accepts both IPv4 and IPv6
+        #[serde(default = "default_bind_address")]
+        bind: IpAddr,
+
+        #[serde(default)]
+        dual_stack: bool,
+
+        // Random ports are picked from this range (e.g. `"30000-39999"`)
+        // instead of the OS ephemeral pool
+        port_range: Option<PortRange>,
+
+        // Never handed out, even if explicitly requested
+        #[serde(default)]
+        reserved_ports: Vec<u16>,
+    }
+}
+
+#[cfg(feature = "tcp")]
+impl TcpProxiesConfig {
+    pub fn is_reserved(&self, port: u16) -> bool {
+        self.reserved_ports.contains(&port)
+    }
+
+    /// Whether proxy bound on the `port` expects the PROXY
+    /// header
+    pub fn accepts_proxy_protocol(&self, port: u16) -> bool {
+        self.accept_proxy_protocol
+            || self
+                .proxy_protocol_ports
+                .iter()
+                .any(|range| range.contains(port))
+    }
+}
+
+#[cfg(feature = "tcp")]
+impl Default for TcpProxiesConfig {
+    fn default() -> Self {
+        Self {
+            accept_proxy_protocol: false,
+            proxy_protocol_ports: Vec::new(),
+            claim_timeout: default_claim_timeout(),
+            bind: default_bind_address(),
+            dual_stack: false,
+            port_range: None,
+            reserved_ports: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "tcp")]
+const fn default_claim_timeout() -> u64 {
+    30
+}
+
+#[cfg(feature = "tcp")]
+const fn default_bind_address() -> IpAddr {
+    IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+}
+
+/// Inclusive range of ports in the `start-end` form
+#[cfg(feature = "tcp")]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+#[serde(try_from = "String", into = "String")]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+#[cfg(feature = "tcp")]
+impl PortRange {
+    /// Number of ports in the range
+    pub const fn count(self) -> u32 {
+        (self.end - self.start) as u32 + 1
+    }
+
+    pub const fn contains(self, port: u16) -> bool {
+        self.start <= port && port <= self.end
+    }
+}
+
+#[cfg(feature = "tcp")]
+impl TryFrom<String> for PortRange {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid =
+            || format!("invalid port range {value:?}, expected `start-end`");
+
+        let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+        let start: u16 = start.trim().parse().map_err(|_| invalid())?;
+        let end: u16 = end.trim().parse().map_err(|_| invalid())?;
+        if start == 0 || start > end {
+            return Err(invalid());
+        }
+
+        Ok(Self { start, end })
+    }
+}
+
+#[cfg(feature = "tcp")]
+impl From<PortRange> for String {
+    fn from(value: PortRange) -> Self {
+        value.to_string()
+    }
+}
+
+#[cfg(feature = "tcp")]
+impl fmt::Display for PortRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
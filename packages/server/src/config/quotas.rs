@@ -0,0 +1,45 @@
+use std::{
+    collections::HashMap,
+    num::NonZeroU64,
+    path::PathBuf,
+};
+
+entity! {
+    struct QuotasConfig {
+        // Bytes used in the current period are persisted there,
+        // so the restart doesn't reset them
+        database: Option<PathBuf>,
+
+        // Days in the period, usage is reset once it ends
+        #[serde(default = "default_period")]
+        period: u64,
+
+        // Bytes per period (both directions), for the users without
+        // the override. Unlimited if not set
+        default: Option<NonZeroU64>,
+
+        // Overrides of the `default`, keyed by the user name
+        #[serde(default)]
+        users: HashMap<String, NonZeroU64>,
+    }
+}
+
+impl QuotasConfig {
+    /// Quota of the `user`, falling back to the default one
+    pub fn limit_of(&self, user: &str) -> Option<u64> {
+        self.users
+            .get(user)
+            .or(self.default.as_ref())
+            .copied()
+            .map(u64::from)
+    }
+
+    /// Length of the period in seconds
+    pub const fn period_secs(&self) -> u64 {
+        self.period.saturating_mul(24 * 60 * 60)
+    }
+}
+
+const fn default_period() -> u64 {
+    30
+}
@@ -0,0 +1,42 @@
+use std::{
+    collections::HashMap,
+    num::NonZeroU64,
+};
+
+entity! {
+    #[derive(Default)]
+    struct RateLimitsConfig {
+        // Shared by all proxies of the user without the override
+        #[serde(default)]
+        default: Bandwidth,
+
+        // Shared by all flows of a single proxy
+        #[serde(default)]
+        proxy: Bandwidth,
+
+        // Overrides of the `default`, keyed by the user name
+        #[serde(default)]
+        users: HashMap<String, Bandwidth>,
+    }
+
+    // Bytes per second, unlimited if not set
+    #[derive(Default, Clone, Copy)]
+    struct Bandwidth {
+        // Sent by the client to the public peers
+        upload: Option<NonZeroU64>,
+
+        // Received by the client from the public peers
+        download: Option<NonZeroU64>,
+    }
+}
+
+impl RateLimitsConfig {
+    /// Limits of the `user`, falling back to the default
+    /// ones
+    pub fn of_user(&self, user: &str) -> Bandwidth {
+        self.users
+            .get(user)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
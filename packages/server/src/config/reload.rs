@@ -0,0 +1,34 @@
+use tokio::sync::{
+    mpsc,
+    oneshot,
+};
+
+/// Sections that require restart to be applied, or the
+/// reason config was rejected
+pub type ReloadOutcome = Result<Vec<&'static str>, String>;
+
+/// Pending reload request, answered once config is reloaded
+pub type ReloadRequest = oneshot::Sender<ReloadOutcome>;
+
+/// Asks the config reloader to re-read the config file
+#[derive(Clone)]
+pub struct Reloader {
+    tx: mpsc::Sender<ReloadRequest>,
+}
+
+impl Reloader {
+    /// Creates reloader along with the receiving side for
+    /// the config reloader
+    pub fn new() -> (Self, mpsc::Receiver<ReloadRequest>) {
+        let (tx, rx) = mpsc::channel(1);
+        (Self { tx }, rx)
+    }
+
+    /// Reloads config, returns nothing if the reloader is
+    /// not running
+    pub async fn reload(&self) -> Option<ReloadOutcome> {
+        let (reply, rx) = oneshot::channel();
+        self.tx.send(reply).await.ok()?;
+        rx.await.ok()
+    }
+}
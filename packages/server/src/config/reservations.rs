@@ -0,0 +1,45 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+};
+
+entity! {
+    struct ReservationsConfig {
+        // On-disk database of the ports users had last time. Ports
+        // are not remembered if not specified
+        database: Option<PathBuf>,
+
+        // Days after which the remembered port of the user that
+        // didn't take it is given away
+        #[serde(default = "default_forget_after")]
+        forget_after: u64,
+
+        // Static reservations (user name -> port), take precedence
+        // over the remembered ones. Reservation only protects the
+        // port from other clients if its user has own password in
+        // `security.users`, otherwise anyone with the universal
+        // password can log in under that name
+        #[serde(default)]
+        ports: HashMap<String, u16>,
+    }
+}
+
+impl ReservationsConfig {
+    pub const fn forget_after_secs(&self) -> u64 {
+        self.forget_after.saturating_mul(24 * 60 * 60)
+    }
+}
+
+impl Default for ReservationsConfig {
+    fn default() -> Self {
+        Self {
+            database: None,
+            forget_after: default_forget_after(),
+            ports: HashMap::new(),
+        }
+    }
+}
+
+const fn default_forget_after() -> u64 {
+    30
+}
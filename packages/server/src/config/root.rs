@@ -1,12 +1,23 @@
 use std::{
+    collections::HashSet,
     fs,
     path::Path,
 };
 
 use color_eyre::eyre;
 
+#[cfg(feature = "admin")]
+use super::admin::AdminConfig;
+#[cfg(feature = "metrics")]
+use super::metrics::MetricsConfig;
 use super::{
+    accounting::AccountingConfig,
+    limits::LimitsConfig,
     logging::LoggingConfig,
+    proxies::ProxiesConfig,
+    quotas::QuotasConfig,
+    rate_limits::RateLimitsConfig,
+    reservations::ReservationsConfig,
     runtime::RuntimeConfig,
     security::SecurityConfig,
     server::ServerConfig,
@@ -20,6 +31,32 @@ entity! {
 
         #[serde(default)]
         runtime: RuntimeConfig,
+
+        #[serde(default)]
+        proxies: ProxiesConfig,
+
+        #[serde(default)]
+        reservations: ReservationsConfig,
+
+        #[serde(default)]
+        rate_limits: RateLimitsConfig,
+
+        #[serde(default)]
+        limits: LimitsConfig,
+
+        #[serde(default)]
+        accounting: Option<AccountingConfig>,
+
+        #[serde(default)]
+        quotas: Option<QuotasConfig>,
+
+        #[cfg(feature = "admin")]
+        #[serde(default)]
+        admin: Option<AdminConfig>,
+
+        #[cfg(feature = "metrics")]
+        #[serde(default)]
+        metrics: Option<MetricsConfig>,
     }
 }
 
@@ -29,6 +66,172 @@ impl Config {
         tracing::debug!("Loading config from the {}", path.display());
 
         let contents = fs::read_to_string(path)?;
-        toml::from_str(&contents).map_err(From::from)
+        let config: Self = toml::from_str(&contents)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Checks invariants that can't be expressed through
+    /// the types
+    pub fn validate(&self) -> eyre::Result<()> {
+        if self.server.name.len() > u8::MAX as usize {
+            eyre::bail!("server.name must not exceed 255 bytes");
+        }
+
+        let runtime = &self.runtime;
+        if runtime.user.is_none()
+            && (runtime.group.is_some() || runtime.keep_net_bind_service)
+        {
+            eyre::bail!(
+                "runtime.group and runtime.keep_net_bind_service require \
+                 runtime.user"
+            );
+        }
+
+        if self
+            .accounting
+            .as_ref()
+            .is_some_and(|a| a.interval == 0)
+        {
+            eyre::bail!("accounting.interval must be positive");
+        }
+        if self
+            .quotas
+            .as_ref()
+            .is_some_and(|q| q.period == 0)
+        {
+            eyre::bail!("quotas.period must be positive");
+        }
+        #[cfg(feature = "tcpflux")]
+        if self.server.protocols.tcp_flux.handshake_timeout == 0 {
+            eyre::bail!(
+                "server.protocols.tcp_flux.handshake_timeout must be positive"
+            );
+        }
+        #[cfg(feature = "tcp")]
+        if self.proxies.tcp.claim_timeout == 0 {
+            eyre::bail!("proxies.tcp.claim_timeout must be positive");
+        }
+
+        #[cfg(feature = "admin")]
+        if self
+            .admin
+            .as_ref()
+            .is_some_and(|a| a.is_exposed() && a.token.is_none())
+        {
+            eyre::bail!(
+                "admin.token must be set if admin.listen is not a loopback address \
+                 or unix socket"
+            );
+        }
+
+        if self.reservations.forget_after == 0 {
+            eyre::bail!("reservations.forget_after must be positive");
+        }
+
+        let mut reserved = HashSet::new();
+        for (user, &port) in &self.reservations.ports {
+            if port == 0 {
+                eyre::bail!("reservation of the {user:?} has zero port");
+            }
+            if !reserved.insert(port) {
+                eyre::bail!("port {port} is reserved for several users");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sections that differ from the `new` config and can't
+    /// be applied without the restart
+    pub fn restart_required(&self, new: &Self) -> Vec<&'static str> {
+        let mut sections = Vec::new();
+        if self.server.protocols != new.server.protocols {
+            sections.push("server.protocols");
+        }
+        if self.server.handover_socket != new.server.handover_socket {
+            sections.push("server.handover_socket");
+        }
+        if self.runtime != new.runtime {
+            sections.push("runtime");
+        }
+        if self.reservations.database != new.reservations.database {
+            sections.push("reservations.database");
+        }
+        if self.quotas.as_ref().map(|q| &q.database)
+            != new.quotas.as_ref().map(|q| &q.database)
+        {
+            sections.push("quotas.database");
+        }
+        #[cfg(feature = "admin")]
+        if self
+            .admin
+            .as_ref()
+            .map(|a| (&a.listen, a.socket_mode))
+            != new
+                .admin
+                .as_ref()
+                .map(|a| (&a.listen, a.socket_mode))
+        {
+            sections.push("admin.listen");
+        }
+        #[cfg(feature = "metrics")]
+        if self
+            .metrics
+            .as_ref()
+            .map(|m| (&m.listen, m.socket_mode))
+            != new
+                .metrics
+                .as_ref()
+                .map(|m| (&m.listen, m.socket_mode))
+        {
+            sections.push("metrics.listen");
+        }
+
+        sections
+    }
+
+    /// Puts sections of this config that can't be applied
+    /// without the restart into the `new` one, so the
+    /// running server keeps using what it was started
+    /// with. Returns the sections that were changed
+    pub fn carry_over_restart_required(&self, new: &mut Self) -> Vec<&'static str> {
+        let sections = self.restart_required(new);
+
+        new.server.protocols = self.server.protocols.clone();
+        new.server.handover_socket = self.server.handover_socket.clone();
+        new.runtime = self.runtime.clone();
+        new.reservations.database = self.reservations.database.clone();
+        if let Some(ref mut quotas) = new.quotas {
+            quotas.database = self
+                .quotas
+                .as_ref()
+                .and_then(|q| q.database.clone());
+        }
+        #[cfg(feature = "admin")]
+        match (&self.admin, &mut new.admin) {
+            (Some(old), Some(current)) => {
+                current.listen = old.listen.clone();
+                current.socket_mode = old.socket_mode;
+                // New config was validated against the other
+                // address
+                if current.is_exposed() && current.token.is_none() {
+                    current.token = old.token.clone();
+                }
+            }
+            // Endpoint is either still running or was never started
+            (old, current) => *current = old.clone(),
+        }
+        #[cfg(feature = "metrics")]
+        match (&self.metrics, &mut new.metrics) {
+            (Some(old), Some(current)) => {
+                current.listen = old.listen.clone();
+                current.socket_mode = old.socket_mode;
+            }
+            (old, current) => *current = old.clone(),
+        }
+
+        sections
     }
 }
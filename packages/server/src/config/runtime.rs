@@ -1,8 +1,21 @@
 use std::num::NonZeroUsize;
 
 entity! {
-    #[derive(Default)]
+    #[derive(Default, Clone)]
     struct RuntimeConfig {
-        threads: Option<NonZeroUsize>
+        threads: Option<NonZeroUsize>,
+
+        // Unprivileged user to switch to once the listeners
+        // are bound
+        user: Option<String>,
+
+        // Primary group of the `user` if not specified
+        group: Option<String>,
+
+        // Retain `CAP_NET_BIND_SERVICE` after switching the user
+        // (linux only), so TCP proxies still can bind ports below
+        // 1024
+        #[serde(default)]
+        keep_net_bind_service: bool,
     }
 }
@@ -1,6 +1,21 @@
+use std::collections::HashMap;
+
+use flux_common::Rights;
+
 entity! {
     struct SecurityConfig {
         // Used as the alternative authentication method (without involving database)
         universal_password: Option<String>,
+
+        // Passwords of the individual users (user name -> password).
+        // Listed users can't be impersonated with the universal
+        // password, so their reserved ports are protected
+        #[serde(default)]
+        users: HashMap<String, String>,
+
+        // Granted after the successful authentication, e.g.
+        // `"CAN_CREATE_TCP_PROXY | CAN_PICK_TCP_PORT"`
+        #[serde(default = "Rights::all")]
+        rights: Rights,
     }
 }
@@ -1,20 +1,132 @@
-use std::net::SocketAddr;
+use std::{
+    fmt,
+    net::SocketAddr,
+    path::PathBuf,
+};
 
 entity! {
     struct ServerConfig {
         name: String,
-        protocols: ProtocolsConfig
+        protocols: ProtocolsConfig,
+
+        // Seconds given to the active flows to finish on shutdown
+        #[serde(default = "default_drain_period")]
+        drain_period: u64,
+
+        // Unix socket used to pass listening sockets to the
+        // upgraded binary started with the same config. Only the
+        // endpoint listeners are passed: active sessions, flows
+        // and proxies stay with the old process until it drains,
+        // clients reconnect to the new one
+        handover_socket: Option<PathBuf>,
     }
 }
 
 entity! {
+    #[derive(Clone)]
     struct ProtocolsConfig {
         #[cfg(feature = "tcpflux")]
         tcp_flux: TcpFlux
     }
 
     #[cfg(feature = "tcpflux")]
+    #[derive(Clone)]
     struct TcpFlux {
+        listen: ListenAddress,
+
+        // Permission bits of the unix socket file (e.g. `0o660`)
+        socket_mode: Option<u32>,
+
+        // Plaintext if not specified. Applies to the websocket
+        // endpoint as well
+        #[cfg(feature = "tls")]
+        tls: Option<TlsConfig>,
+
+        #[cfg(feature = "websocket")]
+        websocket: Option<WebSocketConfig>,
+
+        // Expect PROXY protocol header (v1 or v2) before anything
+        // else, e.g. when running behind the TCP load balancer
+        #[serde(default)]
+        accept_proxy_protocol: bool,
+
+        // Seconds given to the new connection to complete the TLS,
+        // PROXY and WebSocket handshakes and select its type
+        #[serde(default = "default_handshake_timeout")]
+        handshake_timeout: u64,
+    }
+
+    // Additional HTTP endpoint that accepts tcpflux connections
+    // tunneled through the WebSocket
+    #[cfg(feature = "websocket")]
+    #[derive(Clone)]
+    struct WebSocketConfig {
         listen: SocketAddr,
+
+        #[serde(default = "default_websocket_path")]
+        path: String,
+
+        #[serde(default)]
+        accept_proxy_protocol: bool,
+    }
+
+    #[cfg(feature = "tls")]
+    #[derive(Clone)]
+    struct TlsConfig {
+        // PEM-encoded certificate chain, leaf certificate goes first
+        certificate: PathBuf,
+        key: PathBuf,
     }
 }
+
+/// Either `ip:port` or `unix:/path/to/socket`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ListenAddress {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl ListenAddress {
+    const UNIX_PREFIX: &'static str = "unix:";
+}
+
+impl TryFrom<String> for ListenAddress {
+    type Error = std::net::AddrParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.strip_prefix(Self::UNIX_PREFIX) {
+            Some(path) => Ok(Self::Unix(PathBuf::from(path))),
+            None => value.parse().map(Self::Tcp),
+        }
+    }
+}
+
+impl From<ListenAddress> for String {
+    fn from(value: ListenAddress) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for ListenAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(address) => write!(f, "{address}"),
+            Self::Unix(path) => write!(f, "{}{}", Self::UNIX_PREFIX, path.display()),
+        }
+    }
+}
+
+const fn default_drain_period() -> u64 {
+    30
+}
+
+#[cfg(feature = "tcpflux")]
+const fn default_handshake_timeout() -> u64 {
+    10
+}
+
+#[cfg(feature = "websocket")]
+fn default_websocket_path() -> String {
+    "/tcpflux".to_owned()
+}
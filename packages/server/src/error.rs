@@ -9,6 +9,30 @@ pub enum NonCriticalError {
 
     #[error("access denied")]
     AccessDenied,
+
+    #[error("requested port is reserved")]
+    PortIsReserved,
+
+    #[error("requested port is already in use")]
+    PortUnavailable,
+
+    #[error("server is shutting down")]
+    ShuttingDown,
+
+    #[error("data quota is exceeded")]
+    QuotaExceeded,
+
+    #[error("too many proxies in the session")]
+    TooManyProxies,
+
+    #[error("too many sessions of the user")]
+    TooManySessions,
+
+    #[error("too many flows of the proxy")]
+    TooManyFlows,
+
+    #[error("too many pending connections of the proxy")]
+    TooManyPending,
 }
 
 #[derive(Error)]
@@ -25,4 +49,7 @@ pub enum CriticalError {
 
     #[error("failed to bind address")]
     FailedToBind,
+
+    #[error("disconnected by the administrator")]
+    Disconnected,
 }
@@ -0,0 +1,275 @@
+//! Zero-downtime upgrades: the running process passes its
+//! listening sockets (tcpflux, websocket, admin and metrics
+//! endpoints) to the new one over the unix socket. Nothing
+//! else is handed over: established sessions, their flows
+//! and the public listeners of the proxies stay with the
+//! old process until they finish or the drain period ends,
+//! the clients have to reconnect to the new one
+
+use std::{
+    fs,
+    io::{
+        self,
+        IoSlice,
+        IoSliceMut,
+        Read,
+        Write,
+    },
+    os::{
+        fd::{
+            AsRawFd,
+            FromRawFd,
+            OwnedFd,
+            RawFd,
+        },
+        unix::{
+            fs::PermissionsExt,
+            net::{
+                UnixListener as StdUnixListener,
+                UnixStream,
+            },
+        },
+    },
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use nix::sys::socket::{
+    recvmsg,
+    sendmsg,
+    ControlMessage,
+    ControlMessageOwned,
+    MsgFlags,
+};
+use tokio::net::UnixListener;
+
+use crate::sockets::Sockets;
+
+/// Upper bound of the sockets passed in the single
+/// handover
+const MAX_SOCKETS: usize = 16;
+
+/// Sent by the receiver once it took ownership of the
+/// sockets
+const ACK: u8 = b'+';
+
+const EXCHANGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Receives listening sockets from the previous process
+/// that serves handover on the `path`. Returns nothing if
+/// there is no such process.
+pub fn receive(path: &Path) -> io::Result<Vec<(String, OwnedFd)>> {
+    let stream = match UnixStream::connect(path) {
+        Ok(s) => s,
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+            ) =>
+        {
+            return Ok(Vec::new());
+        }
+        Err(e) => return Err(e),
+    };
+
+    receive_from(&stream)
+}
+
+/// Receives sockets over the connected `stream` and
+/// confirms it
+fn receive_from(stream: &UnixStream) -> io::Result<Vec<(String, OwnedFd)>> {
+    stream.set_read_timeout(Some(EXCHANGE_TIMEOUT))?;
+    stream.set_write_timeout(Some(EXCHANGE_TIMEOUT))?;
+
+    // Message is `count` byte followed by the newline
+    // separated names, file descriptors go in the same order
+    let mut buf = [0; 4096];
+    let (read, fds) = {
+        let mut iov = [IoSliceMut::new(&mut buf)];
+        let mut cmsg = nix::cmsg_space!([RawFd; MAX_SOCKETS]);
+        let message = recvmsg::<()>(
+            stream.as_raw_fd(),
+            &mut iov,
+            Some(&mut cmsg),
+            MsgFlags::MSG_CMSG_CLOEXEC,
+        )?;
+
+        let mut fds = Vec::new();
+        for cmsg in message.cmsgs()? {
+            if let ControlMessageOwned::ScmRights(received) = cmsg {
+                fds.extend(received.into_iter().map(|fd| {
+                    // SAFETY: descriptors were just received and
+                    // are not owned by anything else
+                    unsafe { OwnedFd::from_raw_fd(fd) }
+                }));
+            }
+        }
+        if message.flags.contains(MsgFlags::MSG_CTRUNC) {
+            return Err(invalid_data("too many sockets were passed"));
+        }
+
+        (message.bytes, fds)
+    };
+
+    let Some((&count, names)) = buf[..read].split_first() else {
+        return Err(invalid_data("handover message is empty"));
+    };
+    let names = std::str::from_utf8(names).map_err(invalid_data)?;
+    let names: Vec<_> = match count {
+        0 => Vec::new(),
+        _ => names.split('\n').map(str::to_owned).collect(),
+    };
+    if names.len() != usize::from(count) || fds.len() != names.len() {
+        return Err(invalid_data("malformed handover message"));
+    }
+
+    (&*stream).write_all(&[ACK])?;
+    Ok(names.into_iter().zip(fds).collect())
+}
+
+/// Binds the handover socket, replacing the one left by
+/// the previous process. Only the owner can connect to it.
+pub fn bind(path: &Path) -> io::Result<StdUnixListener> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+
+    let listener = StdUnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+
+    Ok(listener)
+}
+
+/// Passes registered sockets to the first process that
+/// confirms receiving them. Returns after the successful
+/// handover, this process must stop accepting then.
+///
+/// Besides the owner, root is allowed to take sockets over,
+/// since the upgraded binary is started privileged if this
+/// process dropped privileges.
+pub async fn serve(
+    listener: StdUnixListener,
+    sockets: Arc<Sockets>,
+) -> io::Result<()> {
+    listener.set_nonblocking(true)?;
+    let listener = UnixListener::from_std(listener)?;
+
+    let owner = nix::unistd::geteuid().as_raw();
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let uid = stream.peer_cred()?.uid();
+        if uid != owner && uid != 0 {
+            tracing::error!("Refused to hand sockets over to the uid {uid}");
+            continue;
+        }
+
+        let stream = stream.into_std()?;
+        let to_send = Arc::clone(&sockets);
+        let result =
+            tokio::task::spawn_blocking(move || send(&stream, &to_send)).await?;
+        match result {
+            Ok(()) => {
+                sockets.mark_handed_over();
+                return Ok(());
+            }
+            Err(e) => tracing::error!("Failed to hand sockets over: {e}"),
+        }
+    }
+}
+
+fn send(stream: &UnixStream, sockets: &Sockets) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(EXCHANGE_TIMEOUT))?;
+    stream.set_write_timeout(Some(EXCHANGE_TIMEOUT))?;
+
+    let sockets = sockets.duplicate()?;
+    if sockets.len() > MAX_SOCKETS {
+        return Err(invalid_data("too many sockets to pass"));
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    let mut message = vec![sockets.len() as u8];
+    for (idx, (name, _)) in sockets.iter().enumerate() {
+        if idx != 0 {
+            message.push(b'\n');
+        }
+        message.extend_from_slice(name.as_bytes());
+    }
+
+    let fds: Vec<_> = sockets
+        .iter()
+        .map(|(_, fd)| fd.as_raw_fd())
+        .collect();
+    let cmsgs = match fds.as_slice() {
+        [] => Vec::new(),
+        fds => vec![ControlMessage::ScmRights(fds)],
+    };
+    sendmsg::<()>(
+        stream.as_raw_fd(),
+        &[IoSlice::new(&message)],
+        &cmsgs,
+        MsgFlags::empty(),
+        None,
+    )?;
+
+    let mut ack = [0];
+    (&*stream).read_exact(&mut ack)?;
+    if ack[0] != ACK {
+        return Err(invalid_data("receiver did not confirm the handover"));
+    }
+
+    Ok(())
+}
+
+fn invalid_data(
+    error: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener as StdTcpListener;
+
+    use super::*;
+
+    /// Hands `sockets` over the socketpair, returns what
+    /// the receiver got
+    fn round_trip(sockets: &Sockets) -> Vec<(String, OwnedFd)> {
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        std::thread::scope(|scope| {
+            let sent = scope.spawn(|| send(&sender, sockets));
+            let received = receive_from(&receiver).unwrap();
+            sent.join().unwrap().unwrap();
+
+            received
+        })
+    }
+
+    #[test]
+    fn listeners_are_passed_with_their_names() {
+        let sockets = Sockets::default();
+        let tcpflux = sockets
+            .bind_tcp("tcpflux", "127.0.0.1:0".parse().unwrap())
+            .unwrap();
+        let admin = sockets
+            .bind_tcp("admin", "127.0.0.1:0".parse().unwrap())
+            .unwrap();
+
+        let received: Vec<_> = round_trip(&sockets)
+            .into_iter()
+            .map(|(name, fd)| (name, StdTcpListener::from(fd).local_addr().unwrap()))
+            .collect();
+        assert_eq!(
+            received,
+            [("tcpflux".to_owned(), tcpflux), ("admin".to_owned(), admin)]
+        );
+    }
+
+    #[test]
+    fn nothing_to_pass() {
+        assert!(round_trip(&Sockets::default()).is_empty());
+    }
+}
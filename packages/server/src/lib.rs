@@ -1,7 +1,20 @@
 pub mod config;
 
+pub mod accounting;
+#[cfg(feature = "admin")]
+pub mod admin;
 pub mod error;
+#[cfg(unix)]
+pub mod handover;
+#[cfg(any(feature = "admin", feature = "metrics"))]
+pub mod local_http;
+#[cfg(feature = "tcpflux")]
+pub mod metrics;
 pub mod protocols;
+pub mod rate_limit;
+pub mod reservations;
+pub mod shutdown;
+pub mod sockets;
 pub mod user;
 
 pub mod proxies;
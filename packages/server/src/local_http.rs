@@ -0,0 +1,336 @@
+//! Minimal HTTP/1.1 server for the local endpoints: single
+//! request per connection, body is expected only with the
+//! `Content-Length`
+
+use std::{
+    future::Future,
+    io,
+    sync::Arc,
+    time::Duration,
+};
+
+use color_eyre::eyre;
+use tcp_flux::transport::{
+    tcp::TcpAcceptor,
+    Acceptor,
+};
+use tokio::io::{
+    AsyncRead,
+    AsyncReadExt,
+    AsyncWrite,
+    AsyncWriteExt,
+};
+
+use crate::{
+    config::server::ListenAddress,
+    shutdown::Shutdown,
+    sockets::Sockets,
+};
+
+const MAX_HEAD_SIZE: usize = 16 * 1024;
+const MAX_BODY_SIZE: usize = 64 * 1024;
+const MAX_HEADERS: usize = 32;
+/// Whole request must be received within it
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub authorization: Option<String>,
+    pub body: Vec<u8>,
+}
+
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+/// Serves requests on the listener named `name` until the
+/// shutdown. Listener must be prepared by the
+/// [`Sockets::bind_listen_address`]
+pub async fn run<H, F>(
+    name: &str,
+    listen: &ListenAddress,
+    sockets: &Sockets,
+    shutdown: Shutdown,
+    handler: H,
+) -> eyre::Result<()>
+where
+    H: Fn(Request) -> F + Clone + Send + Sync + 'static,
+    F: Future<Output = Response> + Send,
+{
+    match *listen {
+        ListenAddress::Tcp(..) => {
+            let acceptor = TcpAcceptor::from(sockets.tcp_listener(name)?);
+            serve(name, acceptor, shutdown, handler).await
+        }
+
+        #[cfg(unix)]
+        ListenAddress::Unix(ref path) => {
+            use tcp_flux::transport::unix::UnixAcceptor;
+
+            use crate::sockets::SocketFileGuard;
+
+            let acceptor = UnixAcceptor::from(sockets.unix_listener(name)?);
+            let _socket_file = SocketFileGuard::new(path, sockets);
+            serve(name, acceptor, shutdown, handler).await
+        }
+
+        #[cfg(not(unix))]
+        ListenAddress::Unix(..) => unreachable!("rejected by the bind"),
+    }
+}
+
+async fn serve<A, H, F>(
+    name: &str,
+    acceptor: A,
+    shutdown: Shutdown,
+    handler: H,
+) -> eyre::Result<()>
+where
+    A: Acceptor,
+    H: Fn(Request) -> F + Clone + Send + Sync + 'static,
+    F: Future<Output = Response> + Send,
+{
+    let acceptor = Arc::new(acceptor);
+    loop {
+        let accept_result = tokio::select! {
+            biased;
+            () = shutdown.triggered() => {
+                return Ok(());
+            }
+
+            accept_result = acceptor.accept() => {
+                accept_result
+            }
+        };
+        let (incoming, mut address) = match accept_result {
+            Ok(a) => a,
+            Err(e) => {
+                tracing::error!("{name} endpoint failed to accept connection: {e}");
+                continue;
+            }
+        };
+
+        let acceptor = Arc::clone(&acceptor);
+        let handler = handler.clone();
+        let name = name.to_owned();
+        tokio::spawn(async move {
+            let result = async {
+                let mut stream = acceptor.upgrade(incoming, &mut address).await?;
+                let read =
+                    tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream));
+                let Some(request) = read.await.map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "request was not received in time",
+                    )
+                })??
+                else {
+                    return Ok(());
+                };
+
+                let response = handler(request).await;
+                write_response(&mut stream, &response).await
+            };
+            if let Err(e) = result.await {
+                tracing::error!("{name} request from {address} failed: {e}");
+            }
+        });
+    }
+}
+
+/// Reads the request. Returns nothing if peer closed the
+/// connection before sending anything.
+async fn read_request<S>(stream: &mut S) -> io::Result<Option<Request>>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut buf = Vec::with_capacity(1024);
+    loop {
+        let mut chunk = [0; 1024];
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            if buf.is_empty() {
+                return Ok(None);
+            }
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buf.extend_from_slice(&chunk[..read]);
+
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut request = httparse::Request::new(&mut headers);
+        let head_size = match request.parse(&buf).map_err(invalid_data)? {
+            httparse::Status::Complete(size) => size,
+            httparse::Status::Partial if buf.len() < MAX_HEAD_SIZE => continue,
+            httparse::Status::Partial => {
+                return Err(invalid_data("request head is too large"));
+            }
+        };
+
+        let mut content_length = 0;
+        let mut authorization = None;
+        for header in request.headers.iter() {
+            if header.name.eq_ignore_ascii_case("content-length") {
+                content_length = std::str::from_utf8(header.value)
+                    .ok()
+                    .and_then(|v| v.trim().parse().ok())
+                    .ok_or_else(|| invalid_data("invalid Content-Length"))?;
+            } else if header.name.eq_ignore_ascii_case("authorization") {
+                authorization =
+                    Some(String::from_utf8_lossy(header.value).into_owned());
+            }
+        }
+        if content_length > MAX_BODY_SIZE {
+            return Err(invalid_data("request body is too large"));
+        }
+
+        let method = request.method.unwrap_or_default().to_owned();
+        let path = request.path.unwrap_or_default().to_owned();
+
+        let mut body = buf.split_off(head_size);
+        if body.len() < content_length {
+            let already_read = body.len();
+            body.resize(content_length, 0);
+            stream
+                .read_exact(&mut body[already_read..])
+                .await?;
+        }
+        body.truncate(content_length);
+
+        return Ok(Some(Request {
+            method,
+            path,
+            authorization,
+            body,
+        }));
+    }
+}
+
+async fn write_response<S>(stream: &mut S, response: &Response) -> io::Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: \
+         close\r\n\r\n",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&response.body).await?;
+    stream.flush().await
+}
+
+const fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        422 => "Unprocessable Entity",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Internal Server Error",
+    }
+}
+
+fn invalid_data(
+    error: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn read(mut raw: &[u8]) -> io::Result<Option<Request>> {
+        read_request(&mut raw).await
+    }
+
+    #[tokio::test]
+    async fn head_and_body_are_parsed() {
+        let request = read(
+            b"PUT /sessions/1/rights HTTP/1.1\r\nAuthorization: Bearer tk\r\n\
+              content-length: 5\r\n\r\nhello",
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.path, "/sessions/1/rights");
+        assert_eq!(request.authorization.as_deref(), Some("Bearer tk"));
+        assert_eq!(request.body, b"hello");
+    }
+
+    #[tokio::test]
+    async fn body_is_read_after_the_head() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        let writer = tokio::spawn(async move {
+            client
+                .write_all(b"POST /reload HTTP/1.1\r\nContent-Length: 4\r\n\r\n")
+                .await
+                .unwrap();
+            tokio::task::yield_now().await;
+            client.write_all(b"body").await.unwrap();
+            client
+        });
+
+        let request = read_request(&mut server).await.unwrap().unwrap();
+        assert_eq!(request.body, b"body");
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn bytes_past_content_length_are_ignored() {
+        let request = read(b"GET /usage HTTP/1.1\r\nContent-Length: 2\r\n\r\nabcd")
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(request.body, b"ab");
+    }
+
+    #[tokio::test]
+    async fn missing_content_length_means_no_body() {
+        let request = read(b"GET /sessions HTTP/1.1\r\n\r\nleftover")
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(request.body.is_empty());
+        assert_eq!(request.authorization, None);
+    }
+
+    #[tokio::test]
+    async fn closed_connection_is_not_a_request() {
+        assert!(read(b"").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn malformed_requests_are_rejected() {
+        for (raw, kind) in [
+            (
+                &b"GET / HTTP/1.1\r\nContent-Length: five\r\n\r\n"[..],
+                io::ErrorKind::InvalidData,
+            ),
+            (
+                b"GET / HTTP/1.1\r\nContent-Length: 1000000\r\n\r\n",
+                io::ErrorKind::InvalidData,
+            ),
+            (b"GET / HTTP/1.1\r\nHost: x", io::ErrorKind::UnexpectedEof),
+            (
+                b"GET / HTTP/1.1\r\nContent-Length: 10\r\n\r\nshort",
+                io::ErrorKind::UnexpectedEof,
+            ),
+        ] {
+            assert_eq!(read(raw).await.err().map(|e| e.kind()), Some(kind));
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    fmt::{
+        self,
+        Write,
+    },
+    sync::{
+        atomic::Ordering,
+        Arc,
+    },
+};
+
+use color_eyre::eyre;
+use tcp_flux::types::error_code::ErrorCode;
+
+use super::METRICS;
+use crate::{
+    config::{
+        handle::ConfigHandle,
+        root::Config,
+    },
+    error::NonCriticalError,
+    local_http::{
+        self,
+        Request,
+        Response,
+    },
+    protocols::tcp_flux::sessions::Sessions,
+    proxies::queues::Queues,
+    shutdown::Shutdown,
+    sockets::Sockets,
+};
+
+/// Name under which listener is passed to the upgraded
+/// process
+const METRICS_SOCKET: &str = "metrics";
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Binds the metrics listener if it is configured
+pub fn prepare(config: &Config, sockets: &Sockets) -> eyre::Result<()> {
+    let Some(ref metrics) = config.metrics else {
+        return Ok(());
+    };
+
+    let address = sockets.bind_listen_address(
+        METRICS_SOCKET,
+        &metrics.listen,
+        metrics.socket_mode,
+    )?;
+    tracing::info!("Metrics endpoint is listening on {address}");
+
+    Ok(())
+}
+
+/// Serves the metrics endpoint until the shutdown
+pub async fn run(
+    config: ConfigHandle,
+    sessions: Sessions,
+    queues: Queues,
+    sockets: Arc<Sockets>,
+    shutdown: Shutdown,
+) -> eyre::Result<()> {
+    let config = config.get();
+    let Some(ref metrics) = config.metrics else {
+        return Ok(());
+    };
+
+    let handler = move |request: Request| {
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/metrics") => Response {
+                status: 200,
+                content_type: CONTENT_TYPE,
+                body: render(&sessions, &queues).into_bytes(),
+            },
+            _ => Response {
+                status: 404,
+                content_type: CONTENT_TYPE,
+                body: b"metrics are served at /metrics\n".to_vec(),
+            },
+        };
+        async move { response }
+    };
+    local_http::run(METRICS_SOCKET, &metrics.listen, &sockets, shutdown, handler)
+        .await
+}
+
+fn render(sessions: &Sessions, queues: &Queues) -> String {
+    let mut out = String::new();
+    // Writing into the string can't fail
+    _ = write_all(&mut out, sessions, queues);
+    out
+}
+
+fn write_all(out: &mut String, sessions: &Sessions, queues: &Queues) -> fmt::Result {
+    header(
+        out,
+        "fluxus_masters_active",
+        "gauge",
+        "Connected master sessions",
+    )?;
+    writeln!(out, "fluxus_masters_active {}", sessions.len())?;
+
+    header(out, "fluxus_proxies_active", "gauge", "Running proxies")?;
+    #[cfg(feature = "tcp")]
+    writeln!(
+        out,
+        "fluxus_proxies_active{{kind=\"tcp\"}} {}",
+        queues.tcp.queues()
+    )?;
+
+    header(
+        out,
+        "fluxus_pending_connections",
+        "gauge",
+        "Accepted connections not claimed by the flow yet",
+    )?;
+    #[cfg(feature = "tcp")]
+    writeln!(
+        out,
+        "fluxus_pending_connections{{kind=\"tcp\"}} {}",
+        queues.tcp.pending()
+    )?;
+
+    counter(
+        out,
+        "fluxus_flows_claimed_total",
+        "Public connections claimed by the flows",
+        &METRICS.flows_claimed,
+    )?;
+    counter(
+        out,
+        "fluxus_flows_closed_total",
+        "Closed flows",
+        &METRICS.flows_closed,
+    )?;
+    counter(
+        out,
+        "fluxus_connections_shed_total",
+        "Public connections closed right away since the master lagged behind",
+        &METRICS.connections_shed,
+    )?;
+    counter(
+        out,
+        "fluxus_claims_expired_total",
+        "Public connections closed since the client did not claim them in time",
+        &METRICS.claims_expired,
+    )?;
+    counter(
+        out,
+        "fluxus_claims_rejected_total",
+        "Flows closed since they presented the wrong secret or nothing was pending",
+        &METRICS.claims_rejected,
+    )?;
+
+    header(
+        out,
+        "fluxus_forwarded_bytes_total",
+        "counter",
+        "Bytes forwarded between the public peers and the clients",
+    )?;
+    for (direction, value) in [
+        ("received", &METRICS.bytes_received),
+        ("sent", &METRICS.bytes_sent),
+    ] {
+        writeln!(
+            out,
+            "fluxus_forwarded_bytes_total{{direction=\"{direction}\"}} {}",
+            value.load(Ordering::Relaxed)
+        )?;
+    }
+
+    header(
+        out,
+        "fluxus_rejected_requests_total",
+        "counter",
+        "Requests of the clients rejected with the non-critical error",
+    )?;
+    for (idx, value) in METRICS.rejected_requests.iter().enumerate() {
+        let Ok(error) = NonCriticalError::try_from(idx as u8) else {
+            continue;
+        };
+        writeln!(
+            out,
+            "fluxus_rejected_requests_total{{reason=\"{}\"}} {}",
+            snake_case(&format!("{error:?}")),
+            value.load(Ordering::Relaxed)
+        )?;
+    }
+
+    header(
+        out,
+        "fluxus_errors_sent_total",
+        "counter",
+        "Error codes sent to the clients",
+    )?;
+    for (idx, value) in METRICS.errors_sent.iter().enumerate() {
+        let Ok(code) = ErrorCode::try_from(idx as u8) else {
+            continue;
+        };
+        writeln!(
+            out,
+            "fluxus_errors_sent_total{{code=\"{}\"}} {}",
+            snake_case(&format!("{code:?}")),
+            value.load(Ordering::Relaxed)
+        )?;
+    }
+
+    Ok(())
+}
+
+fn header(out: &mut String, name: &str, type_: &str, help: &str) -> fmt::Result {
+    writeln!(out, "# HELP {name} {help}")?;
+    writeln!(out, "# TYPE {name} {type_}")
+}
+
+fn counter(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    value: &std::sync::atomic::AtomicU64,
+) -> fmt::Result {
+    header(out, name, "counter", help)?;
+    writeln!(out, "{name} {}", value.load(Ordering::Relaxed))
+}
+
+/// `AccessDenied` -> `access_denied`
+fn snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (idx, c) in name.char_indices() {
+        if c.is_ascii_uppercase() && idx != 0 {
+            out.push('_');
+        }
+        out.push(c.to_ascii_lowercase());
+    }
+
+    out
+}
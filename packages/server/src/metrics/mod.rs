@@ -0,0 +1,97 @@
+//! Process-wide counters, exposed in the Prometheus text
+//! format by the metrics endpoint
+
+use std::sync::atomic::{
+    AtomicU64,
+    Ordering,
+};
+
+use tcp_flux::types::error_code::ErrorCode;
+
+use crate::error::NonCriticalError;
+
+#[cfg(feature = "metrics")]
+pub mod endpoint;
+
+pub static METRICS: Metrics = Metrics::new();
+
+pub struct Metrics {
+    flows_claimed: AtomicU64,
+    flows_closed: AtomicU64,
+    connections_shed: AtomicU64,
+    claims_expired: AtomicU64,
+    claims_rejected: AtomicU64,
+    bytes_received: AtomicU64,
+    bytes_sent: AtomicU64,
+
+    // Indexed by the error discriminant
+    rejected_requests: [AtomicU64; 256],
+    errors_sent: [AtomicU64; 256],
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            flows_claimed: AtomicU64::new(0),
+            flows_closed: AtomicU64::new(0),
+            connections_shed: AtomicU64::new(0),
+            claims_expired: AtomicU64::new(0),
+            claims_rejected: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+            rejected_requests: [const { AtomicU64::new(0) }; 256],
+            errors_sent: [const { AtomicU64::new(0) }; 256],
+        }
+    }
+
+    /// Public connection was claimed by the flow
+    pub fn flow_claimed(&self) {
+        self.flows_claimed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Claimed connection was closed
+    pub fn flow_closed(&self) {
+        self.flows_closed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Public connection was closed right away since the
+    /// master lagged behind
+    pub fn connection_shed(&self) {
+        self.connections_shed
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Public connection was closed since the client did
+    /// not claim it in time
+    pub fn claim_expired(&self) {
+        self.claims_expired
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Flow was closed since it presented the wrong secret
+    /// or there was nothing to claim
+    pub fn claim_rejected(&self) {
+        self.claims_rejected
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Bytes received from the public peers
+    pub fn add_received(&self, bytes: usize) {
+        self.bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Bytes sent to the public peers
+    pub fn add_sent(&self, bytes: usize) {
+        self.bytes_sent
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn request_rejected(&self, error: NonCriticalError) {
+        self.rejected_requests[error as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn error_sent(&self, code: ErrorCode) {
+        self.errors_sent[code as usize].fetch_add(1, Ordering::Relaxed);
+    }
+}
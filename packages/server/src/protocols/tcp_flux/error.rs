@@ -1,14 +1,21 @@
 use std::io;
 
 use tcp_flux::{
+    connection::{
+        master::writer::server::MasterServerWriter,
+        traits::RawWrite,
+    },
     error::PktBaseReadError,
     types::error_code::ErrorCode,
 };
 use thiserror::Error;
 
-use crate::error::{
-    CriticalError,
-    NonCriticalError,
+use crate::{
+    error::{
+        CriticalError,
+        NonCriticalError,
+    },
+    metrics::METRICS,
 };
 
 pub type TcpFluxResult<T> = Result<T, TcpFluxError>;
@@ -37,6 +44,7 @@ pub const fn convert_critical(error: CriticalError) -> ErrorCode {
         C::ChannelClosed => E::InternalError,
         C::ServerWasShut => E::Shutdown,
         C::FailedToBind => E::InternalError,
+        C::Disconnected => E::Disconnected,
     }
 }
 
@@ -46,6 +54,22 @@ pub const fn convert_non_critical(error: NonCriticalError) -> ErrorCode {
 
     match error {
         N::FailedToAuthenticate => E::AuthenticationFailure,
-        N::AccessDenied => E::AccessDenied,
+        N::AccessDenied | N::PortIsReserved => E::AccessDenied,
+        N::PortUnavailable => E::PortUnavailable,
+        N::ShuttingDown => E::Shutdown,
+        N::QuotaExceeded => E::QuotaExceeded,
+        N::TooManyProxies => E::TooManyProxies,
+        N::TooManySessions => E::TooManySessions,
+        N::TooManyFlows => E::TooManyFlows,
+        N::TooManyPending => E::TooManyPending,
     }
 }
+
+/// Reports error to the client
+pub async fn send_error<W: RawWrite>(
+    writer: &mut MasterServerWriter<W>,
+    code: ErrorCode,
+) -> io::Result<()> {
+    METRICS.error_sent(code);
+    writer.write_error(code).await
+}
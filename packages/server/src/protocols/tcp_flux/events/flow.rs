@@ -1,19 +1,34 @@
-use std::sync::Arc;
-
 use tokio::sync::{
     mpsc,
-    Notify,
+    oneshot,
 };
 
 use super::master::FlowMasterCommand;
 
 #[derive(Debug)]
 pub struct FlowHandshake {
-    pub notifier: Arc<Notify>,
+    /// Fired by the flow that claimed the connection,
+    /// dropped if the connection is refused
+    pub claimed: oneshot::Sender<()>,
     pub flow_tx: mpsc::Sender<FlowMasterCommand>,
     pub master_rx: mpsc::Receiver<FlowEvent>,
 }
 
+impl FlowHandshake {
+    /// Closes the public connection without it ever being
+    /// claimed by the flow
+    pub fn refuse(self) {
+        // Handler sees the dropped sender and exits without
+        // metering the connection
+        drop(self);
+    }
+
+    /// Whether the handler stopped waiting for the claim
+    pub fn is_abandoned(&self) -> bool {
+        self.claimed.is_closed()
+    }
+}
+
 #[derive(Debug)]
 pub enum FlowEvent {
     Wrote { buf: Vec<u8> },
@@ -1,4 +1,11 @@
+use std::sync::Arc;
+
+use flux_common::Rights;
+use tcp_flux::proxy_protocol::ProxiedAddresses;
+use tokio::sync::oneshot;
+
 use super::flow::FlowHandshake;
+use crate::protocols::tcp_flux::sessions::SessionInfo;
 
 #[derive(Debug)]
 pub enum FlowMasterCommand {
@@ -8,6 +15,50 @@ pub enum FlowMasterCommand {
 
 #[derive(Debug)]
 pub enum MasterEvent {
-    Connected { handshake: FlowHandshake },
-    ShutdownServer,
+    /// Public connection was accepted by the proxy bound on
+    /// the `port`
+    Connected {
+        port: u16,
+        handshake: FlowHandshake,
+        addresses: ProxiedAddresses,
+    },
+    /// Listener of the proxy bound on the `port` was
+    /// stopped
+    ShutdownServer {
+        port: u16,
+    },
+    /// Connection accepted by the proxy bound on the `port`
+    /// was not claimed in time and is closed
+    ClaimExpired {
+        port: u16,
+    },
+    /// Config was replaced, session should pick up the
+    /// changes that apply to it
+    ConfigReloaded,
+    /// The `user` ran out of the data quota, their proxies
+    /// must be closed
+    QuotaExceeded {
+        user: Arc<str>,
+    },
+    Admin(AdminCommand),
+}
+
+/// Requests of the administrator to the session
+#[derive(Debug)]
+pub enum AdminCommand {
+    Describe {
+        reply: oneshot::Sender<SessionInfo>,
+    },
+    Disconnect {
+        reason: String,
+    },
+    /// Replies whether the proxy was found
+    CloseProxy {
+        port: u16,
+        reply: oneshot::Sender<bool>,
+    },
+    SetRights {
+        rights: Rights,
+        reply: oneshot::Sender<()>,
+    },
 }
@@ -0,0 +1,118 @@
+use std::{
+    num::NonZeroU32,
+    time::Duration,
+};
+
+use flux_common::address::PeerAddress;
+use tcp_flux::connection::any::ClaimSecret;
+use tokio::io::{
+    AsyncRead,
+    AsyncReadExt,
+    AsyncWrite,
+    AsyncWriteExt,
+};
+
+use crate::{
+    metrics::METRICS,
+    protocols::tcp_flux::{
+        error::TcpFluxResult,
+        events::{
+            flow::{
+                FlowEvent,
+                FlowHandshake,
+            },
+            master::FlowMasterCommand,
+        },
+    },
+    proxies::{
+        connection_queue::ClaimError,
+        queues::Queues,
+        tcp::listener::BUFFER_SIZE,
+    },
+};
+
+/// Rejected flow is closed only after it, so the claims
+/// can't be retried in a tight loop
+const REJECTED_CLAIM_DELAY: Duration = Duration::from_secs(1);
+
+/// Claims pending connection `id` of the proxy bound on the
+/// `port` (the oldest one if `id` is not specified) and
+/// forwards data between it and the flow until either side
+/// is closed
+pub async fn handle_flow<R, W>(
+    mut reader: R,
+    mut writer: W,
+    queues: &Queues,
+    address: PeerAddress,
+    port: u16,
+    secret: &ClaimSecret,
+    id: Option<NonZeroU32>,
+) -> TcpFluxResult<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let handshake = match queues.tcp.claim(&port, secret, id) {
+        Ok(handshake) => handshake,
+        Err(e) => {
+            METRICS.claim_rejected();
+            match e {
+                ClaimError::WrongSecret => tracing::warn!(
+                    "{address} tried to claim connection of the {port} with the \
+                     wrong secret"
+                ),
+                ClaimError::NothingToClaim => match id {
+                    Some(id) => tracing::warn!(
+                        "{address} found no pending connection {id} of the {port}"
+                    ),
+                    None => tracing::warn!(
+                        "{address} found no pending connections of the {port}"
+                    ),
+                },
+            }
+
+            tokio::time::sleep(REJECTED_CLAIM_DELAY).await;
+            return Ok(());
+        }
+    };
+    let FlowHandshake {
+        claimed,
+        flow_tx,
+        mut master_rx,
+    } = handshake;
+    // Claim raced with the expiry, the connection is gone
+    if claimed.send(()).is_err() {
+        tracing::warn!("{address} claimed expired connection of the {port}");
+        return Ok(());
+    }
+
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        tokio::select! {
+            event = master_rx.recv() => {
+                match event {
+                    Some(FlowEvent::Wrote { buf }) => {
+                        writer.write_all(&buf).await?;
+                    }
+
+                    Some(FlowEvent::Closed) | None => {
+                        return Ok(());
+                    }
+                }
+            }
+
+            read_result = reader.read(&mut buffer) => {
+                let read @ 1.. = read_result? else {
+                    _ = flow_tx.send(FlowMasterCommand::Close).await;
+                    return Ok(());
+                };
+
+                if flow_tx.send(
+                    FlowMasterCommand::Forward { buf: Vec::from(&buffer[..read]) }
+                ).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use std::sync::Arc;
+use std::{
+    num::NonZeroU32,
+    sync::Arc,
+    time::Duration,
+};
 
 use color_eyre::eyre;
 use tcp_flux::{
@@ -9,46 +13,332 @@ use tcp_flux::{
             writer::server::MasterServerWriter,
         },
     },
-    listener::Listener,
+    listener::{
+        Incoming,
+        Listener,
+    },
+    transport::{
+        proxied::ProxyProtocolAcceptor,
+        tcp::TcpAcceptor,
+        Acceptor,
+    },
+};
+use tokio::{
+    io,
+    sync::oneshot,
+    time::timeout,
 };
 
 use super::master::network::connection::ConnectionState;
 use crate::{
-    config::root::Config,
-    protocols::tcp_flux::master::handler::handle_connection,
-    proxies::queues::Queues,
+    config::{
+        root::Config,
+        server::ListenAddress,
+    },
+    protocols::tcp_flux::{
+        flow::handler::handle_flow,
+        master::handler::handle_connection,
+        shared::Shared,
+    },
+    sockets::Sockets,
 };
 
-pub async fn run(queues: Queues, config: Arc<Config>) -> eyre::Result<()> {
-    let listener = Listener::bind(config.server.protocols.tcp_flux.listen).await?;
-    let bound_address = listener.inner_ref().local_addr()?;
+/// Pause after the failed accept
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Names under which listeners are passed to the upgraded
+/// process
+const PRIMARY_SOCKET: &str = "tcpflux";
+#[cfg(feature = "websocket")]
+const WEBSOCKET_SOCKET: &str = "tcpflux-websocket";
+
+/// TLS configuration shared by all tcpflux endpoints
+#[derive(Default)]
+struct TlsLayer {
+    #[cfg(feature = "tls")]
+    config: Option<Arc<tcp_flux::tls::rustls::ServerConfig>>,
+}
+
+impl TlsLayer {
+    #[cfg(feature = "tls")]
+    fn load(config: &Config) -> eyre::Result<Self> {
+        use tcp_flux::tls::server::ServerTls;
+
+        let Some(ref tls) = config.server.protocols.tcp_flux.tls else {
+            return Ok(Self::default());
+        };
+
+        let tls = ServerTls::load(&tls.certificate, &tls.key)?;
+        tracing::info!("tcpflux TLS certificate fingerprint: {}", tls.fingerprint);
+
+        Ok(Self {
+            config: Some(tls.config),
+        })
+    }
+
+    #[cfg(not(feature = "tls"))]
+    fn load(_config: &Config) -> eyre::Result<Self> {
+        Ok(Self::default())
+    }
+}
+
+/// Endpoints that are ready to be served
+pub struct Endpoints {
+    tls: TlsLayer,
+}
+
+/// Binds listeners of all configured tcpflux endpoints and
+/// loads the TLS certificate. Runtime is not required, so
+/// it can be done before dropping privileges.
+pub fn prepare(config: &Config, sockets: &Sockets) -> eyre::Result<Endpoints> {
+    let tcp_flux = &config.server.protocols.tcp_flux;
+    let address = sockets.bind_listen_address(
+        PRIMARY_SOCKET,
+        &tcp_flux.listen,
+        tcp_flux.socket_mode,
+    )?;
+    tracing::info!("tcpflux is listening on {address}");
+
+    #[cfg(feature = "websocket")]
+    if let Some(ref websocket) = tcp_flux.websocket {
+        let address = sockets.bind_tcp(WEBSOCKET_SOCKET, websocket.listen)?;
+        tracing::info!(
+            "tcpflux websocket endpoint is listening on {address}{}",
+            websocket.path
+        );
+    }
+
+    Ok(Endpoints {
+        tls: TlsLayer::load(config)?,
+    })
+}
+
+/// Serves endpoints bound by the [`prepare`], `ready` is
+/// signaled once all of them are accepting
+pub async fn run(
+    endpoints: Endpoints,
+    shared: Shared,
+    sockets: Arc<Sockets>,
+    ready: oneshot::Sender<()>,
+) -> eyre::Result<()> {
+    // Listeners are configured once, changes to them require
+    // the restart
+    let snapshot = shared.config.get();
+    let tls = endpoints.tls;
+
+    #[cfg(feature = "websocket")]
+    if let Some(ref websocket) = snapshot.server.protocols.tcp_flux.websocket {
+        // Taken first, so the primary endpoint is the last one
+        // to become ready
+        let acceptor = TcpAcceptor::from(sockets.tcp_listener(WEBSOCKET_SOCKET)?);
+        let websocket = run_websocket(websocket, acceptor, &tls, shared.clone());
+        let primary = run_primary(&snapshot, &sockets, &tls, shared, ready);
+        tokio::try_join!(primary, websocket)?;
+
+        return Ok(());
+    }
+
+    run_primary(&snapshot, &sockets, &tls, shared, ready).await
+}
+
+async fn run_primary(
+    config: &Config,
+    sockets: &Sockets,
+    tls: &TlsLayer,
+    shared: Shared,
+    ready: oneshot::Sender<()>,
+) -> eyre::Result<()> {
+    match config.server.protocols.tcp_flux.listen {
+        ListenAddress::Tcp(..) => {
+            let acceptor = TcpAcceptor::from(sockets.tcp_listener(PRIMARY_SOCKET)?);
+            _ = ready.send(());
+
+            serve_with_proxy_protocol(acceptor, tls, shared).await
+        }
+
+        #[cfg(unix)]
+        ListenAddress::Unix(ref path) => {
+            use tcp_flux::transport::unix::UnixAcceptor;
+
+            use crate::sockets::SocketFileGuard;
+
+            let acceptor =
+                UnixAcceptor::from(sockets.unix_listener(PRIMARY_SOCKET)?);
+            let _socket_file = SocketFileGuard::new(path, sockets);
+            _ = ready.send(());
 
-    tracing::info!("tcpflux is listening on {bound_address}");
+            serve_with_proxy_protocol(acceptor, tls, shared).await
+        }
+
+        #[cfg(not(unix))]
+        ListenAddress::Unix(..) => unreachable!("rejected by the prepare"),
+    }
+}
+
+#[cfg(feature = "websocket")]
+#[cfg_attr(not(feature = "tls"), allow(unused_variables))]
+async fn run_websocket(
+    websocket: &crate::config::server::WebSocketConfig,
+    acceptor: TcpAcceptor,
+    tls: &TlsLayer,
+    shared: Shared,
+) -> eyre::Result<()> {
+    if websocket.accept_proxy_protocol {
+        let acceptor = ProxyProtocolAcceptor::new(acceptor);
+        return serve_websocket(acceptor, &websocket.path, tls, shared).await;
+    }
+
+    serve_websocket(acceptor, &websocket.path, tls, shared).await
+}
+
+#[cfg(feature = "websocket")]
+#[cfg_attr(not(feature = "tls"), allow(unused_variables))]
+async fn serve_websocket<A: Acceptor>(
+    acceptor: A,
+    path: &str,
+    tls: &TlsLayer,
+    shared: Shared,
+) -> eyre::Result<()> {
+    use tcp_flux::transport::websocket::WsAcceptor;
+
+    #[cfg(feature = "tls")]
+    if let Some(ref tls) = tls.config {
+        use tcp_flux::transport::tls::TlsAcceptor;
+
+        let acceptor = TlsAcceptor::new(acceptor, Arc::clone(tls));
+        let acceptor = WsAcceptor::new(acceptor, path.to_owned());
+        return serve(Listener::new(acceptor), shared).await;
+    }
+
+    let acceptor = WsAcceptor::new(acceptor, path.to_owned());
+    serve(Listener::new(acceptor), shared).await
+}
+
+/// Expects PROXY protocol header in front of everything
+/// else if it is configured
+async fn serve_with_proxy_protocol<A: Acceptor>(
+    acceptor: A,
+    tls: &TlsLayer,
+    shared: Shared,
+) -> eyre::Result<()> {
+    let config = shared.config.get();
+    if config
+        .server
+        .protocols
+        .tcp_flux
+        .accept_proxy_protocol
+    {
+        let acceptor = ProxyProtocolAcceptor::new(acceptor);
+        return serve_with_tls(acceptor, tls, shared).await;
+    }
+
+    serve_with_tls(acceptor, tls, shared).await
+}
+
+/// Wraps acceptor into the TLS if it is configured
+#[cfg_attr(not(feature = "tls"), allow(unused_variables))]
+async fn serve_with_tls<A: Acceptor>(
+    acceptor: A,
+    tls: &TlsLayer,
+    shared: Shared,
+) -> eyre::Result<()> {
+    #[cfg(feature = "tls")]
+    if let Some(ref tls) = tls.config {
+        use tcp_flux::transport::tls::TlsAcceptor;
+
+        let acceptor = TlsAcceptor::new(acceptor, Arc::clone(tls));
+        return serve(Listener::new(acceptor), shared).await;
+    }
+
+    serve(Listener::new(acceptor), shared).await
+}
+
+/// Serves tcpflux over the arbitrary transport until the
+/// shutdown. Each connection is handled in the separate
+/// task.
+pub async fn serve<A: Acceptor>(
+    listener: Listener<A>,
+    shared: Shared,
+) -> eyre::Result<()> {
     loop {
-        let connection = listener.next_connection().await?;
-        let (reader, writer) = connection.socket.into_split();
+        let next_connection = tokio::select! {
+            biased;
+            () = shared.shutdown.triggered() => {
+                return Ok(());
+            }
 
-        let execution_result = match connection.type_ {
-            ConnectionType::Flow { id } => {
-                tracing::info!("{} connected to the flow {id}", connection.address);
-                todo!()
+            next_connection = listener.next_connection() => {
+                next_connection
             }
+        };
+        let incoming = match next_connection {
+            Ok(i) => i,
+            Err(e) => {
+                tracing::error!("tcpflux failed to accept connection: {e}");
 
-            ConnectionType::Master => {
-                tracing::info!("{} connected as the master", connection.address);
-                let state =
-                    ConnectionState::new(&queues, &config, connection.address);
-                handle_connection(
-                    MasterReader::new(reader),
-                    MasterServerWriter::new(writer),
-                    state,
-                )
-                .await
+                // Errors like EMFILE persist for a while, retrying
+                // right away would spin
+                tokio::select! {
+                    () = shared.shutdown.triggered() => return Ok(()),
+                    () = tokio::time::sleep(ACCEPT_ERROR_BACKOFF) => continue,
+                }
             }
         };
 
-        if let Err(e) = execution_result {
-            tracing::error!("{} disconnected ({e})", connection.address);
+        tokio::spawn(handle_incoming(incoming, shared.clone()));
+    }
+}
+
+async fn handle_incoming<A: Acceptor>(incoming: Incoming<A>, shared: Shared) {
+    let peer = incoming.address;
+    let handshake_timeout = Duration::from_secs(
+        shared
+            .config
+            .get()
+            .server
+            .protocols
+            .tcp_flux
+            .handshake_timeout,
+    );
+    let connection = match timeout(handshake_timeout, incoming.handshake()).await {
+        Ok(Ok(c)) => c,
+        Ok(Err(e)) => {
+            tracing::error!("{peer} failed to perform handshake: {e}");
+            return;
+        }
+        Err(..) => {
+            tracing::error!("{peer} did not complete handshake in time");
+            return;
         }
+    };
+    // Might differ from the `incoming.address` if PROXY
+    // protocol header was received
+    let address = connection.address;
+    let (reader, writer) = io::split(connection.socket);
+
+    let execution_result = match connection.type_ {
+        ConnectionType::Flow { port, secret, id } => {
+            tracing::info!(
+                "{address} connected to the flow {} of the {port}",
+                id.map_or(0, NonZeroU32::get)
+            );
+            handle_flow(reader, writer, &shared.queues, address, port, &secret, id)
+                .await
+        }
+
+        ConnectionType::Master => {
+            tracing::info!("{address} connected as the master");
+            let state = ConnectionState::new(&shared, address);
+            handle_connection(
+                MasterReader::new(reader),
+                MasterServerWriter::new(writer),
+                state,
+            )
+            .await
+        }
+    };
+
+    if let Err(e) = execution_result {
+        tracing::error!("{address} disconnected ({e})");
     }
 }
@@ -1,16 +1,32 @@
-use tcp_flux::connection::{
-    master::writer::server::MasterServerWriter,
-    traits::RawWrite,
+use tcp_flux::{
+    connection::{
+        master::{
+            payloads::{
+                connected::ConnectedPayload,
+                expired::ExpiredPayload,
+            },
+            writer::server::MasterServerWriter,
+        },
+        traits::RawWrite,
+    },
+    types::error_code::ErrorCode,
 };
 
 use crate::{
-    error::CriticalError,
+    error::{
+        CriticalError,
+        NonCriticalError,
+    },
     protocols::tcp_flux::{
         error::{
+            send_error,
             TcpFluxError,
             TcpFluxResult,
         },
-        events::master::MasterEvent,
+        events::master::{
+            AdminCommand,
+            MasterEvent,
+        },
         master::network::connection::ConnectionState,
     },
 };
@@ -26,21 +42,176 @@ where
     W: RawWrite,
 {
     match event {
-        MasterEvent::Connected { handshake } => {
-            writer.write_connected().await?;
-            if state
+        MasterEvent::Connected {
+            port,
+            handshake,
+            addresses,
+        } => {
+            // Claim of the connection expired before it was
+            // queued, its handler is gone
+            if handshake.is_abandoned() {
+                return Ok(());
+            }
+
+            // Connection was accepted right before the proxy was
+            // closed by the administrator
+            let Some(proxy) = state.proxy(port) else {
+                handshake.refuse();
+                return Ok(());
+            };
+            if state.require_quota().is_err() {
+                handshake.refuse();
+                return close_over_quota(writer, state).await;
+            }
+
+            let limits = state.limits();
+            if limits
+                .pending_per_proxy
+                .is_some_and(|limit| state.queues.tcp.len(&port) >= limit)
+            {
+                handshake.refuse();
+                return Err(TcpFluxError::NonCritical(
+                    NonCriticalError::TooManyPending,
+                ));
+            }
+            if limits.flows_per_proxy.is_some_and(|limit| {
+                proxy.flows() + state.queues.tcp.len(&port) >= limit
+            }) {
+                handshake.refuse();
+                return Err(TcpFluxError::NonCritical(
+                    NonCriticalError::TooManyFlows,
+                ));
+            }
+
+            let addresses = proxy.forward_addresses.then_some(addresses);
+            let Ok(id) = state.queues.tcp.push(port, handshake) else {
+                return Err(TcpFluxError::Critical(CriticalError::ServerWasShut));
+            };
+
+            writer
+                .write_connected(ConnectedPayload {
+                    port,
+                    secret: state.claim_secret(),
+                    id: id.get(),
+                    addresses,
+                })
+                .await?;
+        }
+
+        // Listener was stopped by us, accepted connections
+        // are still being served
+        MasterEvent::ShutdownServer { .. } if state.is_draining() => {}
+
+        // Same for the proxy closed by the administrator
+        MasterEvent::ShutdownServer { port } if state.proxy(port).is_none() => {}
+
+        MasterEvent::ShutdownServer { .. } => {
+            return Err(TcpFluxError::Critical(CriticalError::ServerWasShut));
+        }
+
+        MasterEvent::ClaimExpired { port } => {
+            // Entry is gone if the flow claimed it in the
+            // meantime or the proxy was closed
+            while let Some((id, _)) = state
                 .queues
                 .tcp
-                .push(state.user.address, handshake)
-                .is_err()
+                .remove_where(&port, |(_, h)| h.is_abandoned())
             {
-                return Err(TcpFluxError::Critical(CriticalError::ServerWasShut));
+                writer
+                    .write_expired(ExpiredPayload { port, id })
+                    .await?;
             }
         }
 
-        MasterEvent::ShutdownServer => {
-            return Err(TcpFluxError::Critical(CriticalError::ServerWasShut));
+        MasterEvent::ConfigReloaded => {
+            state.reload_rate_limits();
+            if let Some(rights) = state.reloaded_rights() {
+                tracing::info!(
+                    "{} rights were changed by the config reload to {rights}",
+                    state.user
+                );
+                state.grant_rights(rights);
+                writer.write_update_rights(rights).await?;
+            }
+        }
+
+        MasterEvent::QuotaExceeded { user } => {
+            if !state.proxies().is_empty()
+                && state.user.name.as_deref() == Some(&*user)
+            {
+                return close_over_quota(writer, state).await;
+            }
+        }
+
+        MasterEvent::Admin(command) => {
+            return handle_admin_command(command, writer, state).await;
+        }
+    }
+    Ok(())
+}
+
+/// Closes the proxies of the user that ran out of the
+/// quota, session stays alive so the client can query it
+async fn close_over_quota<W>(
+    writer: &mut MasterServerWriter<W>,
+    state: &mut ConnectionState<'_>,
+) -> TcpFluxResult<()>
+where
+    W: RawWrite,
+{
+    let ports = state.close_proxies();
+    tracing::info!(
+        "{} proxies on {ports:?} were closed since the data quota is exceeded",
+        state.user
+    );
+    send_error(writer, ErrorCode::QuotaExceeded)
+        .await
+        .map_err(TcpFluxError::Io)
+}
+
+async fn handle_admin_command<W>(
+    command: AdminCommand,
+    writer: &mut MasterServerWriter<W>,
+    state: &mut ConnectionState<'_>,
+) -> TcpFluxResult<()>
+where
+    W: RawWrite,
+{
+    match command {
+        AdminCommand::Describe { reply } => {
+            _ = reply.send(state.describe());
+        }
+
+        AdminCommand::Disconnect { reason } => {
+            tracing::info!(
+                "{} was disconnected by the administrator: {reason}",
+                state.user
+            );
+            return Err(TcpFluxError::Critical(CriticalError::Disconnected));
+        }
+
+        AdminCommand::CloseProxy { port, reply } => {
+            let closed = state.close_proxy(port);
+            _ = reply.send(closed);
+            if closed {
+                tracing::info!(
+                    "{} proxy on the {port} was closed by the administrator",
+                    state.user
+                );
+                send_error(writer, ErrorCode::Shutdown).await?;
+            }
+        }
+
+        AdminCommand::SetRights { rights, reply } => {
+            tracing::info!(
+                "{} rights were changed by the administrator to {rights}",
+                state.user
+            );
+            state.override_rights(rights);
+            _ = reply.send(());
+            writer.write_update_rights(rights).await?;
         }
     }
+
     Ok(())
 }
@@ -1,15 +1,18 @@
-use tcp_flux::connection::{
-    master::{
-        reader::common::{
-            MasterReader,
-            Server,
+use tcp_flux::{
+    connection::{
+        master::{
+            reader::common::{
+                MasterReader,
+                Server,
+            },
+            writer::server::MasterServerWriter,
+        },
+        traits::{
+            RawRead,
+            RawWrite,
         },
-        writer::server::MasterServerWriter,
-    },
-    traits::{
-        RawRead,
-        RawWrite,
     },
+    types::error_code::ErrorCode,
 };
 
 use super::{
@@ -21,9 +24,11 @@ use super::{
 };
 use crate::{
     error::CriticalError,
+    metrics::METRICS,
     protocols::tcp_flux::error::{
         convert_critical,
         convert_non_critical,
+        send_error,
         TcpFluxError,
         TcpFluxResult,
     },
@@ -38,6 +43,7 @@ where
     R: RawRead,
     W: RawWrite,
 {
+    let shutdown = state.shutdown.clone();
     loop {
         let result = tokio::select! {
             network_result = reader.next_packet() => {
@@ -51,7 +57,7 @@ where
                 .await
             }
 
-            event_result = state.event_rx().recv() => {
+            event_result = state.next_event() => {
                 route_event(
                     event_result.ok_or(CriticalError::ChannelClosed)?,
                     &mut writer,
@@ -59,23 +65,29 @@ where
                 )
                 .await
             }
+
+            () = shutdown.triggered(), if !state.is_draining() => {
+                tracing::info!("{} notified about the shutdown", state.user);
+                state.begin_draining();
+                send_error(&mut writer, ErrorCode::Shutdown)
+                    .await
+                    .map_err(TcpFluxError::Io)
+            }
         };
 
         if let Err(e) = result {
             match e {
                 TcpFluxError::Critical(error) => {
                     tracing::error!("{} critical error: {error}", state.user);
-                    return writer
-                        .write_error(convert_critical(error))
+                    return send_error(&mut writer, convert_critical(error))
                         .await
                         .map_err(TcpFluxError::Io);
                 }
 
                 TcpFluxError::NonCritical(error) => {
                     tracing::error!("{} non-critical error: {error}", state.user);
-                    writer
-                        .write_error(convert_non_critical(error))
-                        .await?;
+                    METRICS.request_rejected(error);
+                    send_error(&mut writer, convert_non_critical(error)).await?;
                 }
 
                 other => return Err(other),
@@ -4,7 +4,14 @@ use flux_common::Rights;
 use tcp_flux::{
     connection::{
         master::{
-            payloads::info::InfoPayload,
+            payloads::{
+                info::InfoPayload,
+                quota::QuotaPayload,
+                usage::{
+                    TrafficCounters,
+                    UsagePayload,
+                },
+            },
             reader::server::MasterServerReader,
             writer::server::MasterServerWriter,
         },
@@ -17,9 +24,13 @@ use tcp_flux::{
 };
 
 use super::connection::ConnectionState;
-use crate::protocols::tcp_flux::error::{
-    TcpFluxError,
-    TcpFluxResult,
+use crate::{
+    accounting::TrafficInfo,
+    error::NonCriticalError,
+    protocols::tcp_flux::error::{
+        TcpFluxError,
+        TcpFluxResult,
+    },
 };
 
 /// Indivisible scope of connection: actual packet handling
@@ -46,21 +57,34 @@ impl<'r, 'cfg, R: RawRead, W: RawWrite> Atom<'r, 'cfg, R, W> {
     #[cfg(feature = "tcp")]
     pub async fn create_tcp(mut self) -> TcpFluxResult<()> {
         use std::{
-            net::SocketAddr,
             num::NonZeroU16,
+            sync::Arc,
+            time::Duration,
         };
 
-        use tokio::net::TcpListener;
+        use tcp_flux::connection::master::payloads::tcp_created::TcpCreatedPayload;
 
-        use crate::error::CriticalError;
+        use crate::proxies::tcp::{
+            bind::{
+                bind_tcp_listener,
+                BindError,
+            },
+            listener::{
+                run_tcp_listener,
+                ListenerOptions,
+            },
+        };
 
         let request = self
             .reader
             .read_create_tcp_request(self.flags)
             .await?;
 
+        self.state.require_running()?;
         self.state
             .require_rights(Rights::CAN_CREATE_TCP_PROXY)?;
+        self.state.require_quota()?;
+        self.state.require_proxy_slot()?;
         self.state
             .require_rights(if request.specific_port.is_some() {
                 Rights::CAN_PICK_TCP_PORT
@@ -68,21 +92,111 @@ impl<'r, 'cfg, R: RawRead, W: RawWrite> Atom<'r, 'cfg, R, W> {
                 Rights::empty()
             })?;
 
-        let port = request.specific_port.map_or(0, NonZeroU16::get);
-        // TODO: make bind address configurable
-        let listener = TcpListener::bind(("0.0.0.0", port))
-            .await
-            .map_err(|e| {
+        let user = self.state.user.name.as_deref();
+        let reservations = self.state.reservations;
+        let available = |port: u16| reservations.is_available_for(port, user);
+        let preferred = match request.specific_port {
+            Some(port) if available(port.get()) => Some(port),
+            Some(port) if request.fallback_to_random => {
+                tracing::warn!(
+                    "{} requested port {port} reserved for another user, falling \
+                     back to the random one",
+                    self.state.user
+                );
+                None
+            }
+            Some(port) => {
                 tracing::error!(
-                    "{} failed to bind 0.0.0.0:{port}: {e}",
+                    "{} tried to take port {port} reserved for another user",
                     self.state.user
                 );
-                CriticalError::FailedToBind
-            })?;
-        let token = self
-            .state
-            .create_server(|addr, q| q.tcp.create_queue(addr))?;
-        todo!()
+                return Err(TcpFluxError::NonCritical(
+                    NonCriticalError::PortIsReserved,
+                ));
+            }
+
+            // User gets the reserved port back if didn't ask for
+            // the specific one
+            None => user
+                .and_then(|user| reservations.port_of(user))
+                .and_then(NonZeroU16::new),
+        };
+
+        let config = self.state.config();
+        let proxies = &config.proxies;
+        let mut bind_result = bind_tcp_listener(&proxies.tcp, preferred, available);
+        // Reserved port is only a preference when any port was
+        // requested
+        let may_fall_back =
+            request.fallback_to_random || request.specific_port.is_none();
+        if let Some(port) = preferred.filter(|_| may_fall_back) {
+            if let Err(ref e) = bind_result {
+                tracing::warn!(
+                    "{} can't take port {port} ({e}), falling back to the random \
+                     one",
+                    self.state.user
+                );
+                bind_result = bind_tcp_listener(&proxies.tcp, None, available);
+            }
+        }
+
+        let listener = bind_result.map_err(|e| {
+            tracing::error!("{} failed to create TCP proxy: {e}", self.state.user);
+            match e {
+                BindError::Reserved(..) => {
+                    TcpFluxError::NonCritical(NonCriticalError::PortIsReserved)
+                }
+                // Port may be unavailable for reasons other than being
+                // taken, e.g. lack of privileges, or there may be no free
+                // ports at all. Session is fine anyway
+                BindError::InUse(..) | BindError::Io(..) | BindError::Exhausted => {
+                    TcpFluxError::NonCritical(NonCriticalError::PortUnavailable)
+                }
+            }
+        })?;
+        let bound_on = listener.local_addr()?;
+        let fell_back = request
+            .specific_port
+            .is_some_and(|port| port.get() != bound_on.port());
+
+        let secret = self.state.claim_secret();
+        let token = self.state.create_server(
+            bound_on.port(),
+            request.forward_addresses,
+            |port, q| q.tcp.create_queue(port, secret),
+        )?;
+
+        if let Some(ref user) = self.state.user.name {
+            reservations.remember(user, bound_on.port());
+        }
+
+        tokio::spawn(run_tcp_listener(
+            token,
+            bound_on,
+            listener,
+            ListenerOptions {
+                accept_proxy_protocol: proxies
+                    .tcp
+                    .accepts_proxy_protocol(bound_on.port()),
+                claim_timeout: Duration::from_secs(proxies.tcp.claim_timeout),
+            },
+            self.state.shutdown.clone(),
+            Arc::new(
+                self.state
+                    .meters(bound_on.port())
+                    .expect("proxy was just created"),
+            ),
+            self.state.listener_channel(),
+        ));
+        tracing::info!("{} created TCP proxy on {bound_on}", self.state.user);
+
+        self.writer
+            .write_tcp_created(TcpCreatedPayload {
+                port: bound_on.port(),
+                fell_back,
+            })
+            .await
+            .map_err(TcpFluxError::Io)
     }
 
     #[cfg(feature = "http")]
@@ -93,19 +207,92 @@ impl<'r, 'cfg, R: RawRead, W: RawWrite> Atom<'r, 'cfg, R, W> {
 
 // Service functions (information retrieval, for example)
 impl<'r, 'cfg, R: RawRead, W: RawWrite> Atom<'r, 'cfg, R, W> {
-    /// Try authenticate the user
+    /// Try authenticate the user. On success, user is
+    /// granted configured rights and notified about them
     ///
     /// # Errors
-    pub async fn authenticate(self) -> TcpFluxResult<()> {
-        todo!()
+    /// [`NonCriticalError::FailedToAuthenticate`] if
+    /// password doesn't match or the user is already
+    /// holding the proxy,
+    /// [`NonCriticalError::TooManySessions`] if the user
+    /// has the maximum number of sessions
+    pub async fn authenticate(mut self) -> TcpFluxResult<()> {
+        let request = self.reader.read_authenticate_request().await?;
+
+        let config = self.state.config();
+        let security = &config.security;
+        // Users with own password can't log in with the
+        // universal one
+        let password = match security.users.get(&request.username) {
+            Some(password) => Some(password.as_str()),
+            None => security.universal_password.as_deref(),
+        };
+        let authenticated = !request.username.is_empty()
+            && password == Some(request.password.as_str())
+            // Name can't be changed while holding the proxy,
+            // since it may own reserved port
+            && self.state.proxies().is_empty();
+        if !authenticated {
+            tracing::error!(
+                "{} failed to authenticate as {}",
+                self.state.user,
+                request.username
+            );
+            return Err(TcpFluxError::NonCritical(
+                NonCriticalError::FailedToAuthenticate,
+            ));
+        }
+
+        self.state.authenticate(request.username)?;
+        self.state.grant_rights(security.rights);
+        tracing::info!("{} authenticated", self.state.user);
+
+        self.writer
+            .write_update_rights(self.state.user.rights)
+            .await
+            .map_err(TcpFluxError::Io)
+    }
+
+    /// Sends traffic counters of the user and the current
+    /// proxy to the client
+    pub async fn req_usage(self) -> TcpFluxResult<()> {
+        let (user, proxy) = self.state.usage();
+        let counters = |traffic: TrafficInfo| TrafficCounters {
+            connections: traffic.connections,
+            bytes_received: traffic.bytes_received,
+            bytes_sent: traffic.bytes_sent,
+        };
+
+        self.writer
+            .write_usage(UsagePayload {
+                user: user.map(counters),
+                proxy: proxy.map(counters),
+            })
+            .await
+            .map_err(TcpFluxError::Io)
+    }
+
+    /// Sends data quota of the user to the client
+    pub async fn req_quota(self) -> TcpFluxResult<()> {
+        let quota = self.state.quota().map(|status| QuotaPayload {
+            used: status.used,
+            limit: status.limit,
+            resets_at: status.resets_at,
+        });
+
+        self.writer
+            .write_quota(quota)
+            .await
+            .map_err(TcpFluxError::Io)
     }
 
     /// Sends information about the server to the client
     pub async fn req_info(self) -> TcpFluxResult<()> {
         tracing::info!("{} server information request", self.state.user);
+        let config = self.state.config();
         self.writer
             .write_info(InfoPayload {
-                server_name: Cow::Borrowed(&self.state.config.server.name),
+                server_name: Cow::Borrowed(&config.server.name),
             })
             .await
             .map_err(TcpFluxError::Io)
@@ -129,12 +316,15 @@ impl<'r, 'cfg, R: RawRead, W: RawWrite> Atom<'r, 'cfg, R, W> {
     }
 
     async fn opted_out(self, name: &'static str) -> TcpFluxResult<()> {
+        use tcp_flux::types::error_code::ErrorCode;
+
+        use crate::protocols::tcp_flux::error::send_error;
+
         tracing::error!(
             "{} tried to call opted-out functionality: {name}",
             self.state.user
         );
-        self.writer
-            .write_error(ErrorCode::OptedOut)
+        send_error(self.writer, ErrorCode::OptedOut)
             .await
             .map_err(TcpFluxError::Io)
     }
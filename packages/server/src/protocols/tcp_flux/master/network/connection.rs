@@ -1,16 +1,39 @@
-use std::{
-    net::SocketAddr,
-    sync::Arc,
+use std::sync::{
+    atomic::{
+        AtomicUsize,
+        Ordering,
+    },
+    Arc,
 };
 
-use flux_common::Rights;
+use flux_common::{
+    address::PeerAddress,
+    Rights,
+};
+use tcp_flux::connection::any::ClaimSecret;
 use tokio::sync::{
     mpsc,
+    watch,
     Notify,
 };
 
+#[cfg(feature = "tcp")]
+use crate::proxies::tcp::listener::ListenerChannel;
 use crate::{
-    config::root::Config,
+    accounting::{
+        quotas::{
+            QuotaStatus,
+            Quotas,
+        },
+        Traffic,
+        TrafficInfo,
+        Usage,
+    },
+    config::{
+        handle::ConfigHandle,
+        limits::Limits,
+        root::Config,
+    },
     error::{
         CriticalError,
         NonCriticalError,
@@ -21,29 +44,126 @@ use crate::{
             TcpFluxResult,
         },
         events::master::MasterEvent,
+        sessions::{
+            Meters,
+            ProxyInfo,
+            Registration,
+            SessionId,
+            SessionInfo,
+            SessionStats,
+            TooManySessions,
+        },
+        shared::Shared,
     },
     proxies::{
         connection_queue::QueueAlreadyExists,
         queues::Queues,
     },
+    rate_limit::{
+        RateLimits,
+        Throttle,
+        Throttles,
+    },
+    reservations::Reservations,
+    shutdown::Shutdown,
     user::User,
 };
 
+/// Public connections the master may lag behind on, before
+/// its listeners start dropping them
+const LISTENER_EVENTS_CAPACITY: usize = 64;
+
 struct MasterChannel {
+    // Events of the administrator and the server, the sender is
+    // also kept by the registration in the sessions list.
+    // Listeners report their shutdown and expired claims here
+    // too, cleanup must never wait for the room
     tx: mpsc::UnboundedSender<MasterEvent>,
     rx: mpsc::UnboundedReceiver<MasterEvent>,
+
+    // Events of the proxy listeners, bounded so the flood of
+    // public connections can't outgrow the master
+    listener_tx: mpsc::Sender<MasterEvent>,
+    listener_rx: mpsc::Receiver<MasterEvent>,
+}
+
+/// Proxy server created by the master
+pub struct ProxyHandle {
+    pub port: u16,
+
+    /// Whether addresses of the connections should be sent
+    /// to the client
+    pub forward_addresses: bool,
+
+    shutdown_token: Arc<Notify>,
+    // Cancels the flows, unlike the `shutdown_token` which
+    // only stops the listener
+    closed: watch::Sender<bool>,
+    traffic: Arc<Traffic>,
+    throttle: Arc<Throttle>,
+    flows: Arc<AtomicUsize>,
+}
+
+impl ProxyHandle {
+    /// Claimed connections, pending ones are not counted
+    pub fn flows(&self) -> usize {
+        self.flows.load(Ordering::Relaxed)
+    }
 }
 
 pub struct ConnectionState<'cfg> {
     pub user: User,
     pub queues: &'cfg Queues,
-    pub(super) config: &'cfg Arc<Config>,
+    pub reservations: &'cfg Reservations,
+    pub shutdown: &'cfg Shutdown,
+    pub usage: &'cfg Usage,
+    quotas: &'cfg Arc<Quotas>,
+    rate_limits: &'cfg RateLimits,
+    config: &'cfg ConfigHandle,
 
-    shutdown_token: Option<Arc<Notify>>,
+    proxies: Vec<ProxyHandle>,
     channel: MasterChannel,
+    registration: Registration,
+    stats: Arc<SessionStats>,
+    // Flows must present it to claim connections of the
+    // proxies
+    claim_secret: ClaimSecret,
+    // Totals of the authenticated user
+    user_traffic: Option<Arc<Traffic>>,
+    user_throttle: Option<Arc<Throttle>>,
+
+    // Rights were set by the administrator, config reloads
+    // must not revert them
+    rights_overridden: bool,
+    draining: bool,
 }
 
 impl<'cfg> ConnectionState<'cfg> {
+    /// Snapshot of the current config
+    pub fn config(&self) -> Arc<Config> {
+        self.config.get()
+    }
+
+    /// Secret pending connections of the session are
+    /// claimed with
+    pub const fn claim_secret(&self) -> ClaimSecret {
+        self.claim_secret
+    }
+
+    /// Data quota of the authenticated user
+    pub fn quota(&self) -> Option<QuotaStatus> {
+        self.quotas.status(self.user.name.as_deref()?)
+    }
+
+    pub fn require_quota(&self) -> TcpFluxResult<()> {
+        match self.user.name {
+            Some(ref name) if self.quotas.is_exceeded(name) => {
+                Err(TcpFluxError::NonCritical(NonCriticalError::QuotaExceeded))
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub const fn require_rights(&self, rights: Rights) -> TcpFluxResult<()> {
         if self.user.rights.contains(rights) {
             Ok(())
@@ -51,27 +171,233 @@ impl<'cfg> ConnectionState<'cfg> {
             Err(TcpFluxError::NonCritical(NonCriticalError::AccessDenied))
         }
     }
+
+    /// Limits of the current user
+    pub fn limits(&self) -> Limits {
+        self.config()
+            .limits
+            .of_user(self.user.name.as_deref())
+    }
+
+    /// Marks user as authenticated under the `name`
+    ///
+    /// # Errors
+    /// [`NonCriticalError::TooManySessions`] if the user
+    /// already has the maximum number of sessions
+    pub fn authenticate(&mut self, name: String) -> TcpFluxResult<()> {
+        let config = self.config();
+        let limit = config
+            .limits
+            .of_user(Some(&name))
+            .sessions_per_user;
+        self.registration
+            .assign_user(&name, limit)
+            .map_err(|TooManySessions| {
+                TcpFluxError::NonCritical(NonCriticalError::TooManySessions)
+            })?;
+
+        let bandwidth = config.rate_limits.of_user(&name);
+        self.user_traffic = Some(self.usage.user(&name));
+        self.user_throttle = Some(self.rate_limits.user(&name, bandwidth));
+        self.user.name = Some(name);
+        Ok(())
+    }
+
+    /// Grants rights from the config
+    pub fn grant_rights(&mut self, rights: Rights) {
+        self.user.rights = rights;
+        self.rights_overridden = false;
+    }
+
+    /// Sets rights on behalf of the administrator
+    pub fn override_rights(&mut self, rights: Rights) {
+        self.user.rights = rights;
+        self.rights_overridden = true;
+    }
+
+    /// Rights the user should get after the config was
+    /// reloaded, if they differ from the current ones
+    pub fn reloaded_rights(&self) -> Option<Rights> {
+        if self.user.name.is_none() || self.rights_overridden {
+            return None;
+        }
+
+        let rights = self.config().security.rights;
+        (rights != self.user.rights).then_some(rights)
+    }
+
+    /// Applies bandwidth limits from the reloaded config
+    pub fn reload_rate_limits(&self) {
+        let config = self.config();
+        let rate_limits = &config.rate_limits;
+        for proxy in &self.proxies {
+            proxy.throttle.configure(rate_limits.proxy);
+        }
+        if let (Some(name), Some(throttle)) =
+            (self.user.name.as_deref(), &self.user_throttle)
+        {
+            throttle.configure(rate_limits.of_user(name));
+        }
+    }
 }
 
 impl<'cfg> ConnectionState<'cfg> {
-    pub fn event_rx(&mut self) -> &mut mpsc::UnboundedReceiver<MasterEvent> {
-        &mut self.channel.rx
+    /// Next event for the master, events of the
+    /// administrator and the server take precedence over
+    /// the ones of the listeners
+    pub async fn next_event(&mut self) -> Option<MasterEvent> {
+        let channel = &mut self.channel;
+        tokio::select! {
+            biased;
+            event = channel.rx.recv() => event,
+            event = channel.listener_rx.recv() => event,
+        }
     }
 
-    pub fn event_tx(&self) -> mpsc::UnboundedSender<MasterEvent> {
-        self.channel.tx.clone()
+    /// Senders for the listeners of the proxies
+    #[cfg(feature = "tcp")]
+    pub fn listener_channel(&self) -> ListenerChannel {
+        ListenerChannel {
+            connections: self.channel.listener_tx.clone(),
+            control: self.channel.tx.clone(),
+        }
     }
 }
 
 impl<'cfg> ConnectionState<'cfg> {
+    /// Proxy bound on the `port`
+    pub fn proxy(&self, port: u16) -> Option<&ProxyHandle> {
+        self.proxies
+            .iter()
+            .find(|proxy| proxy.port == port)
+    }
+
+    pub fn proxies(&self) -> &[ProxyHandle] {
+        &self.proxies
+    }
+
+    /// Counters and limits for the flows of the proxy bound
+    /// on the `port`
+    pub fn meters(&self, port: u16) -> Option<Meters> {
+        let proxy = self.proxy(port)?;
+        Some(Meters {
+            session: Arc::clone(&self.stats),
+            proxy: Arc::clone(&proxy.traffic),
+            proxy_flows: Arc::clone(&proxy.flows),
+            user: self.user_traffic.clone(),
+            quota: self
+                .user
+                .name
+                .as_deref()
+                .map(|name| self.quotas.of(name)),
+            throttles: Throttles {
+                proxy: Arc::clone(&proxy.throttle),
+                user: self.user_throttle.clone(),
+            },
+            closed: proxy.closed.subscribe(),
+        })
+    }
+
+    /// Counters of the authenticated user and totals of the
+    /// session proxies
+    pub fn usage(&self) -> (Option<TrafficInfo>, Option<TrafficInfo>) {
+        (
+            self.user_traffic.as_ref().map(|t| t.snapshot()),
+            self.proxies
+                .iter()
+                .map(|p| p.traffic.snapshot())
+                .reduce(|total, traffic| total + traffic),
+        )
+    }
+
+    pub const fn id(&self) -> SessionId {
+        self.registration.id
+    }
+
+    /// Closes the proxy if it is bound on the `port`
+    /// together with its flows, session itself stays
+    /// alive. Returns whether proxy was closed.
+    pub fn close_proxy(&mut self, port: u16) -> bool {
+        let Some(idx) = self.proxies.iter().position(|p| p.port == port) else {
+            return false;
+        };
+
+        let proxy = self.proxies.swap_remove(idx);
+        proxy.shutdown_token.notify_one();
+        proxy.closed.send_replace(true);
+        _ = self.queues.tcp.drop_queue(&proxy.port);
+        self.registration.remove_proxy(proxy.port);
+        true
+    }
+
+    /// Closes all proxies of the session, returns their
+    /// ports
+    pub fn close_proxies(&mut self) -> Vec<u16> {
+        let ports: Vec<_> = self.proxies.iter().map(|p| p.port).collect();
+        for &port in &ports {
+            self.close_proxy(port);
+        }
+
+        ports
+    }
+
+    pub fn describe(&self) -> SessionInfo {
+        SessionInfo {
+            id: self.id(),
+            name: self.user.name.clone(),
+            address: self.user.address.to_string(),
+            rights: self.user.rights,
+            proxies: self
+                .proxies
+                .iter()
+                .map(|proxy| ProxyInfo {
+                    kind: "tcp".to_owned(),
+                    port: proxy.port,
+                    forward_addresses: proxy.forward_addresses,
+                    pending: self.queues.tcp.len(&proxy.port),
+                    flows: proxy.flows(),
+                    traffic: proxy.traffic.snapshot(),
+                })
+                .collect(),
+            draining: self.draining,
+            stats: self.stats.snapshot(),
+        }
+    }
+
+    /// Fails if the session can't hold one more proxy
+    pub fn require_proxy_slot(&self) -> TcpFluxResult<()> {
+        if self
+            .limits()
+            .proxies_per_session
+            .is_some_and(|limit| self.proxies.len() >= limit)
+        {
+            Err(TcpFluxError::NonCritical(NonCriticalError::TooManyProxies))
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn create_server(
         &mut self,
-        creator: impl FnOnce(SocketAddr, &Queues) -> Result<(), QueueAlreadyExists>,
+        port: u16,
+        forward_addresses: bool,
+        creator: impl FnOnce(u16, &Queues) -> Result<(), QueueAlreadyExists>,
     ) -> TcpFluxResult<Arc<Notify>> {
-        match creator(self.user.address, self.queues) {
+        match creator(port, self.queues) {
             Ok(()) => {
                 let token = Arc::new(Notify::new());
-                self.shutdown_token = Some(Arc::clone(&token));
+                self.proxies.push(ProxyHandle {
+                    port,
+                    forward_addresses,
+                    shutdown_token: Arc::clone(&token),
+                    closed: watch::Sender::new(false),
+                    traffic: Arc::default(),
+                    throttle: Arc::new(Throttle::new(
+                        self.config().rate_limits.proxy,
+                    )),
+                    flows: Arc::default(),
+                });
+                self.registration.add_proxy(port);
                 Ok(token)
             }
 
@@ -81,30 +407,69 @@ impl<'cfg> ConnectionState<'cfg> {
 }
 
 impl<'cfg> ConnectionState<'cfg> {
-    pub fn new(
-        queues: &'cfg Queues,
-        config: &'cfg Arc<Config>,
-        address: SocketAddr,
-    ) -> Self {
+    pub const fn is_draining(&self) -> bool {
+        self.draining
+    }
+
+    /// Stops accepting new connections on the proxies,
+    /// while keeping already accepted ones alive
+    pub fn begin_draining(&mut self) {
+        self.draining = true;
+        for proxy in &self.proxies {
+            proxy.shutdown_token.notify_one();
+        }
+    }
+
+    pub const fn require_running(&self) -> TcpFluxResult<()> {
+        if self.draining {
+            Err(TcpFluxError::NonCritical(NonCriticalError::ShuttingDown))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<'cfg> ConnectionState<'cfg> {
+    pub fn new(shared: &'cfg Shared, address: PeerAddress) -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
+        let (listener_tx, listener_rx) = mpsc::channel(LISTENER_EVENTS_CAPACITY);
+        let registration = shared.sessions.register(tx.clone());
         Self {
-            queues,
-            shutdown_token: None,
+            queues: &shared.queues,
+            reservations: &shared.reservations,
+            shutdown: &shared.shutdown,
+            usage: &shared.usage,
+            quotas: &shared.quotas,
+            rate_limits: &shared.rate_limits,
+            proxies: Vec::new(),
+            rights_overridden: false,
+            draining: false,
             user: User::new(Rights::empty(), address),
 
-            channel: MasterChannel { tx, rx },
-            config,
+            channel: MasterChannel {
+                tx,
+                rx,
+                listener_tx,
+                listener_rx,
+            },
+            registration,
+            stats: Arc::default(),
+            claim_secret: rand::random(),
+            user_traffic: None,
+            user_throttle: None,
+            config: &shared.config,
         }
     }
 }
 
 impl<'cfg> Drop for ConnectionState<'cfg> {
     fn drop(&mut self) {
-        if let Some(ref mut token) = self.shutdown_token {
-            token.notify_waiters();
+        for proxy in &self.proxies {
+            // Permit is stored if listener was not polled yet
+            proxy.shutdown_token.notify_one();
 
             // Cleanup queue
-            _ = self.queues.tcp.drop_queue(&self.user.address);
+            _ = self.queues.tcp.drop_queue(&proxy.port);
         }
     }
 }
@@ -49,11 +49,13 @@ where
     match pkt.type_ {
         P::Authenticate => atom.authenticate().await,
         P::ReqInfo => atom.req_info().await,
+        P::Usage => atom.req_usage().await,
+        P::Quota => atom.req_quota().await,
         P::Disconnect => atom.disconnect().await,
         P::CreateHttp => atom.create_http().await,
         P::CreateTcp => atom.create_tcp().await,
 
-        P::Connected | P::Error | P::UpdateRights => {
+        P::Connected | P::Expired | P::Error | P::UpdateRights => {
             Err(TcpFluxError::Critical(CriticalError::UnexpectedPacket))
         }
     }
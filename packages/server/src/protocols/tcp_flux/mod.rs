@@ -1,9 +1,17 @@
 mod error;
 mod listener;
+mod shared;
 
 mod flow;
 mod master;
 
 pub mod events;
+pub mod sessions;
 
-pub use listener::run;
+pub use listener::{
+    prepare,
+    run,
+    serve,
+    Endpoints,
+};
+pub use shared::Shared;
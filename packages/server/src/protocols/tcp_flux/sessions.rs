@@ -0,0 +1,336 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{
+            AtomicU64,
+            AtomicUsize,
+            Ordering,
+        },
+        Arc,
+    },
+};
+
+use dashmap::DashMap;
+use flux_common::Rights;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tokio::sync::{
+    mpsc,
+    watch,
+};
+
+use super::events::master::MasterEvent;
+use crate::{
+    accounting::{
+        quotas::UserQuota,
+        Traffic,
+        TrafficInfo,
+    },
+    metrics::METRICS,
+    rate_limit::Throttles,
+};
+
+pub type SessionId = u64;
+
+#[derive(Default)]
+struct Inner {
+    next_id: AtomicU64,
+    map: DashMap<SessionId, mpsc::UnboundedSender<MasterEvent>>,
+    // Number of sessions authenticated as the user
+    users: DashMap<String, usize>,
+    // Sessions owning the proxies, keyed by the port
+    proxies: DashMap<u16, SessionId>,
+}
+
+/// Live master sessions, reachable through their event
+/// channels
+#[derive(Clone, Default)]
+pub struct Sessions {
+    inner: Arc<Inner>,
+}
+
+/// Keeps session listed until dropped
+pub struct Registration {
+    pub id: SessionId,
+    sessions: Sessions,
+    user: Option<String>,
+}
+
+pub struct TooManySessions;
+
+impl Sessions {
+    pub fn register(&self, tx: mpsc::UnboundedSender<MasterEvent>) -> Registration {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        self.inner.map.insert(id, tx);
+
+        Registration {
+            id,
+            sessions: self.clone(),
+            user: None,
+        }
+    }
+
+    pub fn get(&self, id: SessionId) -> Option<mpsc::UnboundedSender<MasterEvent>> {
+        self.inner.map.get(&id).map(|tx| tx.clone())
+    }
+
+    /// Snapshot of the sessions list
+    pub fn all(&self) -> Vec<(SessionId, mpsc::UnboundedSender<MasterEvent>)> {
+        self.inner
+            .map
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().clone()))
+            .collect()
+    }
+
+    /// Sends event created by the `make_event` to every
+    /// session
+    pub fn broadcast(&self, make_event: impl Fn() -> MasterEvent) {
+        for entry in self.inner.map.iter() {
+            _ = entry.value().send(make_event());
+        }
+    }
+
+    /// Session owning the proxy bound on the `port`
+    pub fn owner_of(&self, port: u16) -> Option<SessionId> {
+        self.inner.proxies.get(&port).map(|owner| *owner)
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.map.is_empty()
+    }
+}
+
+impl Registration {
+    /// Counts the session as the one of the `user`, unless
+    /// the user already has `limit` sessions
+    pub fn assign_user(
+        &mut self,
+        user: &str,
+        limit: Option<usize>,
+    ) -> Result<(), TooManySessions> {
+        if self.user.as_deref() == Some(user) {
+            return Ok(());
+        }
+
+        {
+            let mut count = self
+                .sessions
+                .inner
+                .users
+                .entry(user.to_owned())
+                .or_default();
+            if limit.is_some_and(|limit| *count >= limit) {
+                return Err(TooManySessions);
+            }
+            *count += 1;
+        }
+
+        self.release_user();
+        self.user = Some(user.to_owned());
+        Ok(())
+    }
+
+    /// Lists the session as the owner of the proxy bound on
+    /// the `port`
+    pub fn add_proxy(&self, port: u16) {
+        self.sessions.inner.proxies.insert(port, self.id);
+    }
+
+    pub fn remove_proxy(&self, port: u16) {
+        self.sessions
+            .inner
+            .proxies
+            .remove_if(&port, |_, &owner| owner == self.id);
+    }
+
+    fn release_user(&mut self) {
+        let Some(user) = self.user.take() else {
+            return;
+        };
+        self.sessions
+            .inner
+            .users
+            .remove_if_mut(&user, |_, count| {
+                *count -= 1;
+                *count == 0
+            });
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.release_user();
+        self.sessions
+            .inner
+            .proxies
+            .retain(|_, &mut owner| owner != self.id);
+        self.sessions.inner.map.remove(&self.id);
+    }
+}
+
+/// Counters updated by the flows of the session
+#[derive(Default)]
+pub struct SessionStats {
+    flows: AtomicUsize,
+    received: AtomicU64,
+    sent: AtomicU64,
+}
+
+/// Marks flow of the session and its proxy as active until
+/// dropped
+pub struct ActiveFlow {
+    stats: Arc<SessionStats>,
+    proxy_flows: Arc<AtomicUsize>,
+}
+
+impl SessionStats {
+    /// Bytes received from the public peers
+    pub fn add_received(&self, bytes: usize) {
+        METRICS.add_received(bytes);
+        self.received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Bytes sent to the public peers
+    pub fn add_sent(&self, bytes: usize) {
+        METRICS.add_sent(bytes);
+        self.sent
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> StatsInfo {
+        StatsInfo {
+            flows: self.flows.load(Ordering::Relaxed),
+            bytes_received: self.received.load(Ordering::Relaxed),
+            bytes_sent: self.sent.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Counters the flows of the proxy are accounted in and
+/// limits they are subject to
+pub struct Meters {
+    pub session: Arc<SessionStats>,
+    pub proxy: Arc<Traffic>,
+
+    /// Active flows of the proxy
+    pub proxy_flows: Arc<AtomicUsize>,
+
+    /// Present if the user is authenticated
+    pub user: Option<Arc<Traffic>>,
+
+    /// Present if the user is authenticated
+    pub quota: Option<UserQuota>,
+
+    pub throttles: Throttles,
+
+    /// Set once the proxy is closed
+    pub closed: watch::Receiver<bool>,
+}
+
+impl Meters {
+    /// Accounts the new connection, it is active until the
+    /// returned guard is dropped
+    pub fn track_flow(&self) -> ActiveFlow {
+        for traffic in self.traffic() {
+            traffic.add_connection();
+        }
+
+        METRICS.flow_claimed();
+        self.session.flows.fetch_add(1, Ordering::Relaxed);
+        self.proxy_flows.fetch_add(1, Ordering::Relaxed);
+        ActiveFlow {
+            stats: Arc::clone(&self.session),
+            proxy_flows: Arc::clone(&self.proxy_flows),
+        }
+    }
+
+    pub fn add_received(&self, bytes: usize) {
+        self.session.add_received(bytes);
+        for traffic in self.traffic() {
+            traffic.add_received(bytes);
+        }
+    }
+
+    pub fn add_sent(&self, bytes: usize) {
+        self.session.add_sent(bytes);
+        for traffic in self.traffic() {
+            traffic.add_sent(bytes);
+        }
+    }
+
+    /// Whether the user ran out of the data quota, flows
+    /// must not forward anything past it
+    pub fn is_over_quota(&self) -> bool {
+        self.quota
+            .as_ref()
+            .is_some_and(UserQuota::is_exceeded)
+    }
+
+    /// Resolves once the proxy is closed. Flows of the
+    /// proxy that outlived its session are never cancelled
+    pub fn proxy_closed(&self) -> impl Future<Output = ()> + 'static {
+        let mut closed = self.closed.clone();
+        async move {
+            if closed.wait_for(|&closed| closed).await.is_err() {
+                std::future::pending().await
+            }
+        }
+    }
+
+    fn traffic(&self) -> impl Iterator<Item = &Traffic> {
+        std::iter::once(&*self.proxy).chain(self.user.as_deref())
+    }
+}
+
+impl Drop for ActiveFlow {
+    fn drop(&mut self) {
+        METRICS.flow_closed();
+        self.stats.flows.fetch_sub(1, Ordering::Relaxed);
+        self.proxy_flows.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Session as seen by the administrator
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub id: SessionId,
+    pub name: Option<String>,
+    pub address: String,
+    pub rights: Rights,
+    pub proxies: Vec<ProxyInfo>,
+    pub draining: bool,
+
+    #[serde(flatten)]
+    pub stats: StatsInfo,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProxyInfo {
+    pub kind: String,
+    pub port: u16,
+    pub forward_addresses: bool,
+
+    /// Accepted connections that were not claimed by the
+    /// flow yet
+    pub pending: usize,
+
+    /// Claimed connections, pending ones are not counted
+    pub flows: usize,
+
+    pub traffic: TrafficInfo,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsInfo {
+    pub flows: usize,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+}
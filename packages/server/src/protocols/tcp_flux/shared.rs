@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use super::sessions::Sessions;
+use crate::{
+    accounting::{
+        quotas::Quotas,
+        Usage,
+    },
+    config::handle::ConfigHandle,
+    proxies::queues::Queues,
+    rate_limit::RateLimits,
+    reservations::Reservations,
+    shutdown::Shutdown,
+};
+
+/// State shared by all tcpflux connections
+#[derive(Clone)]
+pub struct Shared {
+    pub queues: Queues,
+    pub reservations: Arc<Reservations>,
+    pub shutdown: Shutdown,
+    pub config: ConfigHandle,
+    pub sessions: Sessions,
+    pub usage: Usage,
+    pub quotas: Arc<Quotas>,
+    pub rate_limits: RateLimits,
+}
+
+impl Shared {
+    pub fn new(
+        config: ConfigHandle,
+        queues: Queues,
+        sessions: Sessions,
+        usage: Usage,
+        quotas: Arc<Quotas>,
+        reservations: Arc<Reservations>,
+        shutdown: Shutdown,
+    ) -> Self {
+        Self {
+            queues,
+            reservations,
+            shutdown,
+            config,
+            sessions,
+            usage,
+            quotas,
+            rate_limits: RateLimits::default(),
+        }
+    }
+}
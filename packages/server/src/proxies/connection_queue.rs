@@ -1,5 +1,6 @@
 use std::{
-    net::SocketAddr,
+    collections::VecDeque,
+    num::NonZeroU32,
     sync::Arc,
 };
 
@@ -8,25 +9,55 @@ use dashmap::{
     DashMap,
 };
 
+/// Secret the items of the queue are claimed with
+pub type Secret = [u8; 16];
+
 pub struct NoSuchQueue;
 pub struct QueueAlreadyExists;
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum ClaimError {
+    WrongSecret,
+    NothingToClaim,
+}
+
+struct Queue<T> {
+    secret: Secret,
+    items: VecDeque<(u32, T)>,
+    // Ids are not reused until the counter wraps, so the late
+    // claim or expiry can't hit another connection
+    next_id: NonZeroU32,
+}
+
+/// Pending connections of the proxies, keyed by the port
+/// proxy is bound on. Connections are served in the order
+/// they were pushed, unless claimed by the id
 pub struct ConnectionQueue<T> {
-    map: Arc<DashMap<SocketAddr, Vec<T>>>,
+    map: Arc<DashMap<u16, Queue<T>>>,
 }
 
 impl<T> ConnectionQueue<T> {
-    pub fn create_queue(&self, key: SocketAddr) -> Result<(), QueueAlreadyExists> {
+    /// Creates queue whose items can be claimed only with
+    /// the `secret`
+    pub fn create_queue(
+        &self,
+        key: u16,
+        secret: Secret,
+    ) -> Result<(), QueueAlreadyExists> {
         match self.map.entry(key) {
             Entry::Occupied(_) => Err(QueueAlreadyExists),
             Entry::Vacant(vacant) => {
-                vacant.insert(Vec::with_capacity(4));
+                vacant.insert(Queue {
+                    secret,
+                    items: VecDeque::with_capacity(4),
+                    next_id: NonZeroU32::MIN,
+                });
                 Ok(())
             }
         }
     }
 
-    pub fn drop_queue(&self, key: &SocketAddr) -> Result<(), NoSuchQueue> {
+    pub fn drop_queue(&self, key: &u16) -> Result<(), NoSuchQueue> {
         if self.map.remove(key).is_some() {
             Ok(())
         } else {
@@ -36,17 +67,77 @@ impl<T> ConnectionQueue<T> {
 }
 
 impl<T> ConnectionQueue<T> {
-    pub fn push(&self, key: SocketAddr, item: T) -> Result<(), NoSuchQueue> {
+    /// Appends the item, returns id it can be claimed with.
+    /// Ids of the queue increase with every push
+    pub fn push(&self, key: u16, item: T) -> Result<NonZeroU32, NoSuchQueue> {
+        let mut queue = self.map.get_mut(&key).ok_or(NoSuchQueue)?;
+        let id = queue.next_id;
+        queue.next_id = id.checked_add(1).unwrap_or(NonZeroU32::MIN);
+        queue.items.push_back((id.get(), item));
+
+        Ok(id)
+    }
+
+    /// Takes the item pushed under the `id`, or the oldest
+    /// one if `id` is not specified
+    pub fn claim(
+        &self,
+        key: &u16,
+        secret: &Secret,
+        id: Option<NonZeroU32>,
+    ) -> Result<T, ClaimError> {
+        let mut queue = self
+            .map
+            .get_mut(key)
+            .ok_or(ClaimError::NothingToClaim)?;
+        if !constant_time_eq(&queue.secret, secret) {
+            return Err(ClaimError::WrongSecret);
+        }
+
+        let idx = match id {
+            Some(id) => queue
+                .items
+                .iter()
+                .position(|&(taken, _)| taken == id.get()),
+            None if queue.items.is_empty() => None,
+            None => Some(0),
+        };
+        idx.and_then(|idx| queue.items.remove(idx))
+            .map(|(_, item)| item)
+            .ok_or(ClaimError::NothingToClaim)
+    }
+
+    /// Removes the oldest item matching the `predicate`,
+    /// returns it together with its id
+    pub fn remove_where(
+        &self,
+        key: &u16,
+        predicate: impl Fn(&(u32, T)) -> bool,
+    ) -> Option<(u32, T)> {
+        let mut queue = self.map.get_mut(key)?;
+        let idx = queue.items.iter().position(predicate)?;
+
+        queue.items.remove(idx)
+    }
+
+    /// Number of pending items, zero if there is no queue
+    pub fn len(&self, key: &u16) -> usize {
         self.map
-            .get_mut(&key)
-            .ok_or(NoSuchQueue)?
-            .push(item);
+            .get(key)
+            .map_or(0, |queue| queue.items.len())
+    }
 
-        Ok(())
+    /// Number of existing queues
+    pub fn queues(&self) -> usize {
+        self.map.len()
     }
 
-    pub fn pop(&self, key: &SocketAddr) -> Option<T> {
-        self.map.get_mut(key)?.pop()
+    /// Number of pending items in all queues
+    pub fn pending(&self) -> usize {
+        self.map
+            .iter()
+            .map(|queue| queue.items.len())
+            .sum()
     }
 }
 
@@ -65,3 +156,100 @@ impl<T> Clone for ConnectionQueue<T> {
         }
     }
 }
+
+fn constant_time_eq(lhs: &Secret, rhs: &Secret) -> bool {
+    lhs.iter()
+        .zip(rhs)
+        .fold(0, |acc, (l, r)| acc | (l ^ r))
+        == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: Secret = [7; 16];
+
+    fn queue_with(
+        items: &[&'static str],
+    ) -> (ConnectionQueue<&'static str>, Vec<NonZeroU32>) {
+        let queue = ConnectionQueue::default();
+        assert!(queue.create_queue(31000, SECRET).is_ok());
+        let ids = items
+            .iter()
+            .map(|&item| queue.push(31000, item).ok().unwrap())
+            .collect();
+
+        (queue, ids)
+    }
+
+    #[test]
+    fn claims_oldest_first() {
+        let (queue, _) = queue_with(&["first", "second", "third"]);
+
+        assert_eq!(queue.claim(&31000, &SECRET, None), Ok("first"));
+        assert_eq!(queue.claim(&31000, &SECRET, None), Ok("second"));
+        assert_eq!(queue.claim(&31000, &SECRET, None), Ok("third"));
+        assert_eq!(
+            queue.claim(&31000, &SECRET, None),
+            Err(ClaimError::NothingToClaim)
+        );
+    }
+
+    #[test]
+    fn claims_exact_item_once() {
+        let (queue, ids) = queue_with(&["first", "second"]);
+
+        assert_eq!(queue.claim(&31000, &SECRET, Some(ids[1])), Ok("second"));
+        assert_eq!(
+            queue.claim(&31000, &SECRET, Some(ids[1])),
+            Err(ClaimError::NothingToClaim)
+        );
+        assert_eq!(queue.len(&31000), 1);
+        assert_eq!(queue.claim(&31000, &SECRET, None), Ok("first"));
+    }
+
+    #[test]
+    fn refuses_wrong_secret() {
+        let (queue, ids) = queue_with(&["first"]);
+
+        assert_eq!(
+            queue.claim(&31000, &[8; 16], Some(ids[0])),
+            Err(ClaimError::WrongSecret)
+        );
+        assert_eq!(
+            queue.claim(&31000, &[8; 16], None),
+            Err(ClaimError::WrongSecret)
+        );
+        assert_eq!(queue.claim(&31000, &SECRET, Some(ids[0])), Ok("first"));
+    }
+
+    #[test]
+    fn nothing_to_claim_without_queue() {
+        let (queue, _) = queue_with(&["first"]);
+
+        assert_eq!(
+            queue.claim(&31001, &SECRET, None),
+            Err(ClaimError::NothingToClaim)
+        );
+        assert!(queue.drop_queue(&31000).is_ok());
+        assert_eq!(
+            queue.claim(&31000, &SECRET, None),
+            Err(ClaimError::NothingToClaim)
+        );
+    }
+
+    #[test]
+    fn ids_are_not_reused() {
+        let (queue, ids) = queue_with(&["first", "second"]);
+        assert_eq!(queue.claim(&31000, &SECRET, Some(ids[1])), Ok("second"));
+
+        let third = queue.push(31000, "third").ok().unwrap();
+        assert!(third > ids[1]);
+        assert_eq!(
+            queue.claim(&31000, &SECRET, Some(ids[1])),
+            Err(ClaimError::NothingToClaim)
+        );
+        assert_eq!(queue.claim(&31000, &SECRET, Some(third)), Ok("third"));
+    }
+}
@@ -0,0 +1,108 @@
+use std::{
+    io,
+    net::SocketAddr,
+    num::NonZeroU16,
+};
+
+use socket2::{
+    Domain,
+    Socket,
+    Type,
+};
+use thiserror::Error;
+use tokio::net::TcpListener;
+
+use crate::config::proxies::TcpProxiesConfig;
+
+// Binds tried before giving up on the random port. They are
+// made right on the runtime worker, so a crowded range must
+// not turn into thousands of syscalls
+const BIND_ATTEMPTS: usize = 16;
+const BACKLOG: i32 = 1024;
+
+#[derive(Debug, Error)]
+pub enum BindError {
+    #[error("port {0} is reserved")]
+    Reserved(u16),
+
+    #[error("port {0} is already in use")]
+    InUse(u16),
+
+    #[error("no free ports left")]
+    Exhausted,
+
+    #[error("{0}")]
+    Io(#[from] io::Error),
+}
+
+/// Binds public listener on the configured interface. If
+/// `port` is not specified, random one is picked from the
+/// configured range (or from the OS ephemeral pool if range
+/// is not set), skipping reserved ports and ports for which
+/// `available` returns `false`. At most [`BIND_ATTEMPTS`]
+/// ports are tried.
+pub fn bind_tcp_listener(
+    config: &TcpProxiesConfig,
+    port: Option<NonZeroU16>,
+    available: impl Fn(u16) -> bool,
+) -> Result<TcpListener, BindError> {
+    if let Some(port) = port {
+        let port = port.get();
+        if config.is_reserved(port) {
+            return Err(BindError::Reserved(port));
+        }
+
+        return bind_on(config, port).map_err(|e| match e.kind() {
+            io::ErrorKind::AddrInUse => BindError::InUse(port),
+            _ => BindError::Io(e),
+        });
+    }
+
+    let Some(range) = config.port_range else {
+        for _ in 0..BIND_ATTEMPTS {
+            let listener = bind_on(config, 0)?;
+            let port = listener.local_addr()?.port();
+            if !config.is_reserved(port) && available(port) {
+                return Ok(listener);
+            }
+        }
+
+        return Err(BindError::Exhausted);
+    };
+
+    // Linear probing from the random offset. Reserved and
+    // unavailable ports are skipped without the syscall, so
+    // only the binds are limited
+    let count = range.count();
+    let offset = rand::random_range(0..count);
+    let candidates = (0..count)
+        .map(|step| range.start + ((offset + step) % count) as u16)
+        .filter(|&port| !config.is_reserved(port) && available(port));
+    for port in candidates.take(BIND_ATTEMPTS) {
+        match bind_on(config, port) {
+            Ok(listener) => return Ok(listener),
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Err(BindError::Exhausted)
+}
+
+fn bind_on(config: &TcpProxiesConfig, port: u16) -> io::Result<TcpListener> {
+    let address = SocketAddr::new(config.bind, port);
+    let socket = Socket::new(Domain::for_address(address), Type::STREAM, None)?;
+
+    if address.is_ipv6() {
+        socket.set_only_v6(!config.dual_stack)?;
+    }
+    // Same as the `tokio::net::TcpListener::bind`
+    #[cfg(not(windows))]
+    socket.set_reuse_address(true)?;
+
+    socket.set_nonblocking(true)?;
+    socket.bind(&address.into())?;
+    socket.listen(BACKLOG)?;
+
+    TcpListener::from_std(socket.into())
+}
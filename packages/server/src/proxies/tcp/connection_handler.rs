@@ -1,7 +1,4 @@
-use std::{
-    io,
-    sync::Arc,
-};
+use std::io;
 
 use tokio::{
     io::{
@@ -9,37 +6,49 @@ use tokio::{
         AsyncWriteExt,
     },
     net::TcpStream,
-    sync::{
-        mpsc,
-        Notify,
-    },
+    sync::mpsc,
 };
 
-use crate::protocols::tcp_flux::events::{
-    flow::FlowEvent,
-    master::FlowMasterCommand,
+use crate::protocols::tcp_flux::{
+    events::{
+        flow::FlowEvent,
+        master::FlowMasterCommand,
+    },
+    sessions::Meters,
 };
 
+/// Forwards data of the claimed connection until either
+/// side closes it, the proxy is closed or the user runs out
+/// of the quota
 pub async fn run_connection_handler(
-    notify: Arc<Notify>,
     buffer_size: usize,
     mut stream: TcpStream,
+    meters: &Meters,
     master_push: &mpsc::Sender<FlowEvent>,
     mut flow_rx: mpsc::Receiver<FlowMasterCommand>,
 ) -> io::Result<()> {
-    // Wait until handshake is performed
-    notify.notified().await;
-
     let mut buffer = vec![0; buffer_size];
+    let proxy_closed = meters.proxy_closed();
+    tokio::pin!(proxy_closed);
     loop {
         tokio::select! {
+            () = &mut proxy_closed => {
+                return Ok(());
+            }
+
             command = flow_rx.recv() => {
                 let Some(command) = command else {
                     return Ok(());
                 };
                 match command {
+                    FlowMasterCommand::Forward { .. } if meters.is_over_quota() => {
+                        return Ok(());
+                    }
+
                     FlowMasterCommand::Forward { buf } => {
+                        meters.throttles.upload(buf.len()).await;
                         stream.write_all(&buf).await?;
+                        meters.add_sent(buf.len());
                     }
 
                     FlowMasterCommand::Close => {
@@ -52,6 +61,11 @@ pub async fn run_connection_handler(
                 let read @ 1.. = read_result? else {
                     return Ok(());
                 };
+                if meters.is_over_quota() {
+                    return Ok(());
+                }
+                meters.add_received(read);
+                meters.throttles.download(read).await;
 
                 if master_push.send(
                     FlowEvent::Wrote { buf: Vec::from(&buffer[..read]) }
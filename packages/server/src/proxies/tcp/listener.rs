@@ -1,35 +1,84 @@
 use std::{
     net::SocketAddr,
     sync::Arc,
+    time::Duration,
 };
 
 use owo_colors::OwoColorize;
+use tcp_flux::proxy_protocol::{
+    read_header,
+    ProxiedAddresses,
+};
 use tokio::{
-    net::TcpListener,
+    net::{
+        TcpListener,
+        TcpStream,
+    },
     sync::{
-        mpsc,
+        mpsc::{
+            self,
+            error::TrySendError,
+        },
+        oneshot,
         Notify,
     },
+    time::timeout,
 };
 
 use super::connection_handler::run_connection_handler;
-use crate::protocols::tcp_flux::events::{
-    flow::{
-        FlowEvent,
-        FlowHandshake,
+use crate::{
+    metrics::METRICS,
+    protocols::tcp_flux::{
+        events::{
+            flow::{
+                FlowEvent,
+                FlowHandshake,
+            },
+            master::MasterEvent,
+        },
+        sessions::Meters,
+    },
+    shutdown::{
+        FlowGuard,
+        Shutdown,
     },
-    master::MasterEvent,
 };
 
 // TODO: make buffer and channel size configurable
 const CHAN_SIZE: usize = 100;
-const BUFFER_SIZE: usize = 4096;
+pub const BUFFER_SIZE: usize = 4096;
+
+// Time given to the load balancer to send the PROXY header
+const PROXY_HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Settings of the listener, taken from the config when
+/// the proxy is created
+#[derive(Debug, Clone, Copy)]
+pub struct ListenerOptions {
+    pub accept_proxy_protocol: bool,
+
+    /// Time client has to claim the accepted connection
+    pub claim_timeout: Duration,
+}
+
+/// Senders of the listener to its master
+pub struct ListenerChannel {
+    /// Accepted connections, bounded so the master is not
+    /// flooded
+    pub connections: mpsc::Sender<MasterEvent>,
+
+    /// Cleanup events, must never wait for the room
+    pub control: mpsc::UnboundedSender<MasterEvent>,
+}
 
 pub async fn run_tcp_listener(
     shutdown_token: Arc<Notify>,
     bound_on: SocketAddr,
     listener: TcpListener,
-    master_push: mpsc::UnboundedSender<MasterEvent>,
+    options: ListenerOptions,
+    shutdown: Shutdown,
+    meters: Arc<Meters>,
+    master: ListenerChannel,
 ) {
     loop {
         let accept_result = tokio::select! {
@@ -49,36 +98,246 @@ pub async fn run_tcp_listener(
                 break;
             }
         };
+        let addresses = ProxiedAddresses {
+            source: address,
+            destination: stream.local_addr().unwrap_or(bound_on),
+        };
+        // Header is read in the separate task, so slow peer
+        // won't block the whole listener
+        tokio::spawn(handle_connection(
+            stream,
+            addresses,
+            options,
+            shutdown.track_flow(),
+            Arc::clone(&meters),
+            master.connections.clone(),
+            master.control.clone(),
+        ));
+    }
+    _ = master.control.send(MasterEvent::ShutdownServer {
+        port: bound_on.port(),
+    });
+}
 
-        tracing::info!("{} connected to the {}", address.bold(), bound_on.bold());
+async fn handle_connection(
+    mut stream: TcpStream,
+    mut addresses: ProxiedAddresses,
+    options: ListenerOptions,
+    // Held until the connection is closed
+    _flow_guard: FlowGuard,
+    meters: Arc<Meters>,
+    master_connections: mpsc::Sender<MasterEvent>,
+    master_control: mpsc::UnboundedSender<MasterEvent>,
+) {
+    let peer = addresses.source;
+    let bound_on = addresses.destination;
 
-        let (flow_tx, flow_rx) = mpsc::channel(CHAN_SIZE);
-        let (master_tx, master_rx) = mpsc::channel(CHAN_SIZE);
+    if options.accept_proxy_protocol {
+        match timeout(PROXY_HEADER_TIMEOUT, read_header(&mut stream)).await {
+            Ok(Ok(Some(real))) => addresses = real,
+            // Health check from the load balancer itself
+            Ok(Ok(None)) => {}
+            Ok(Err(e)) => {
+                tracing::error!(
+                    "{} sent invalid PROXY protocol header: {e}",
+                    peer.bold()
+                );
+                return;
+            }
+            Err(..) => {
+                tracing::error!(
+                    "{} did not send PROXY protocol header in time",
+                    peer.bold()
+                );
+                return;
+            }
+        }
+    }
 
-        let notifier = Arc::new(Notify::new());
-        let handshake = FlowHandshake {
-            flow_tx,
-            master_rx,
-            notifier: Arc::clone(&notifier),
-        };
+    // Lagging master is not waited for, so the connection
+    // never holds the room shared with the other proxies of
+    // the session while idle. Taken only after the header,
+    // so the slow peers can't hold it either
+    let permit = match master_connections.try_reserve_owned() {
+        Ok(permit) => permit,
+        Err(TrySendError::Closed(_)) => return,
+        Err(TrySendError::Full(_)) => {
+            METRICS.connection_shed();
+            tracing::warn!(
+                "{} dropped connection of the {}, master lags behind",
+                bound_on.bold(),
+                addresses.source.bold()
+            );
+            return;
+        }
+    };
+
+    tracing::info!(
+        "{} connected to the {}",
+        addresses.source.bold(),
+        bound_on.bold()
+    );
+
+    let (flow_tx, flow_rx) = mpsc::channel(CHAN_SIZE);
+    let (master_tx, master_rx) = mpsc::channel(CHAN_SIZE);
+
+    let (claimed_tx, claimed_rx) = oneshot::channel();
+    let handshake = FlowHandshake {
+        flow_tx,
+        master_rx,
+        claimed: claimed_tx,
+    };
+
+    let source = addresses.source;
+    permit.send(MasterEvent::Connected {
+        port: bound_on.port(),
+        handshake,
+        addresses,
+    });
+
+    // Wait until handshake is performed
+    match timeout(options.claim_timeout, claimed_rx).await {
+        Ok(Ok(())) => {}
 
-        tokio::spawn(async move {
-            _ = run_connection_handler(
-                notifier,
-                BUFFER_SIZE,
-                stream,
-                &master_tx,
-                flow_rx,
-            )
-            .await;
-            _ = master_tx.send(FlowEvent::Closed).await;
-        });
-        if master_push
-            .send(MasterEvent::Connected { handshake })
-            .is_err()
-        {
-            break;
+        // Refused by the master, never becomes the flow
+        Ok(Err(..)) => return,
+
+        Err(..) => {
+            METRICS.claim_expired();
+            tracing::info!(
+                "{} connection to the {} was not claimed in time",
+                source.bold(),
+                bound_on.bold()
+            );
+            _ = master_control.send(MasterEvent::ClaimExpired {
+                port: bound_on.port(),
+            });
+            return;
+        }
+    }
+
+    // Pending connection becomes the flow once claimed
+    let _active_flow = meters.track_flow();
+    _ = run_connection_handler(BUFFER_SIZE, stream, &meters, &master_tx, flow_rx)
+        .await;
+    _ = master_tx.send(FlowEvent::Closed).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use tokio::{
+        sync::watch,
+        task::JoinHandle,
+    };
+
+    use super::*;
+    use crate::{
+        protocols::tcp_flux::sessions::SessionStats,
+        rate_limit::{
+            Throttle,
+            Throttles,
+        },
+    };
+
+    struct Accepted {
+        handler: JoinHandle<()>,
+        handshake: FlowHandshake,
+        control_rx: mpsc::UnboundedReceiver<MasterEvent>,
+        _client: TcpStream,
+    }
+
+    fn meters(closed: watch::Receiver<bool>) -> Arc<Meters> {
+        Arc::new(Meters {
+            session: Arc::new(SessionStats::default()),
+            proxy: Arc::default(),
+            proxy_flows: Arc::default(),
+            user: None,
+            quota: None,
+            throttles: Throttles {
+                proxy: Arc::new(Throttle::default()),
+                user: None,
+            },
+            closed,
+        })
+    }
+
+    /// Accepts single connection and waits until it is
+    /// queued to the master
+    async fn accept(meters: &Arc<Meters>) -> Accepted {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let bound_on = listener.local_addr().unwrap();
+        let client = TcpStream::connect(bound_on).await.unwrap();
+        let (stream, source) = listener.accept().await.unwrap();
+
+        let (connections_tx, mut connections_rx) = mpsc::channel(1);
+        let (control_tx, control_rx) = mpsc::unbounded_channel();
+        let handler = tokio::spawn(handle_connection(
+            stream,
+            ProxiedAddresses {
+                source,
+                destination: bound_on,
+            },
+            ListenerOptions {
+                accept_proxy_protocol: false,
+                claim_timeout: Duration::from_secs(30),
+            },
+            Shutdown::default().track_flow(),
+            Arc::clone(meters),
+            connections_tx,
+            control_tx,
+        ));
+
+        let Some(MasterEvent::Connected { handshake, .. }) =
+            connections_rx.recv().await
+        else {
+            panic!("connection was not queued");
+        };
+        Accepted {
+            handler,
+            handshake,
+            control_rx,
+            _client: client,
         }
     }
-    _ = master_push.send(MasterEvent::ShutdownServer);
+
+    #[tokio::test]
+    async fn refused_connection_is_not_metered() {
+        let meters = meters(watch::channel(false).1);
+        let mut accepted = accept(&meters).await;
+
+        accepted.handshake.refuse();
+        accepted.handler.await.unwrap();
+
+        assert_eq!(meters.proxy_flows.load(Ordering::Relaxed), 0);
+        assert_eq!(meters.proxy.snapshot().connections, 0);
+        assert_eq!(meters.session.snapshot().flows, 0);
+        // Refusal is not an expiry
+        assert!(accepted.control_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn closing_proxy_cancels_flow() {
+        let (closed_tx, closed_rx) = watch::channel(false);
+        let meters = meters(closed_rx);
+        let accepted = accept(&meters).await;
+
+        // Channels are kept, so only the cancellation can
+        // stop the handler
+        let FlowHandshake {
+            claimed,
+            flow_tx: _flow_tx,
+            master_rx: _master_rx,
+        } = accepted.handshake;
+        claimed.send(()).unwrap();
+        closed_tx.send_replace(true);
+
+        timeout(Duration::from_secs(5), accepted.handler)
+            .await
+            .expect("flow was not cancelled")
+            .unwrap();
+        assert_eq!(meters.proxy.snapshot().connections, 1);
+        assert_eq!(meters.proxy_flows.load(Ordering::Relaxed), 0);
+    }
 }
@@ -1,2 +1,3 @@
+pub mod bind;
 pub mod connection_handler;
 pub mod listener;
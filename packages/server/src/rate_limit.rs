@@ -0,0 +1,218 @@
+//! Bandwidth limits of the proxies and users
+
+use std::{
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+        Arc,
+    },
+    time::Duration,
+};
+
+use dashmap::DashMap;
+use tokio::{
+    sync::Mutex,
+    time::{
+        sleep,
+        Instant,
+    },
+};
+
+use crate::config::rate_limits::Bandwidth;
+
+/// Token bucket holding up to one second worth of traffic.
+/// Waiters are served in the order they came, so flows
+/// sharing the bucket get equal share of the bandwidth
+pub struct TokenBucket {
+    // Bytes per second, zero means unlimited
+    rate: AtomicU64,
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    // Negative if the last taker is still waiting for its
+    // share
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// Upload and download buckets of the single proxy or user
+#[derive(Default)]
+pub struct Throttle {
+    upload: TokenBucket,
+    download: TokenBucket,
+}
+
+/// Throttles the flows of the proxy are limited by
+pub struct Throttles {
+    pub proxy: Arc<Throttle>,
+
+    /// Present if the user is authenticated
+    pub user: Option<Arc<Throttle>>,
+}
+
+/// Throttles of the authenticated users, shared by all of
+/// their sessions
+#[derive(Clone, Default)]
+pub struct RateLimits {
+    users: Arc<DashMap<String, Arc<Throttle>>>,
+}
+
+impl TokenBucket {
+    fn new(rate: u64) -> Self {
+        Self {
+            rate: AtomicU64::new(rate),
+            state: Mutex::new(BucketState {
+                tokens: f64::INFINITY,
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn set_rate(&self, rate: u64) {
+        self.rate.store(rate, Ordering::Relaxed);
+    }
+
+    /// Waits until `amount` bytes can be passed through
+    #[allow(clippy::cast_precision_loss)]
+    pub async fn take(&self, amount: usize) {
+        let rate = self.rate.load(Ordering::Relaxed);
+        if rate == 0 {
+            return;
+        }
+
+        let rate = rate as f64;
+        let mut state = self.state.lock().await;
+        let now = Instant::now();
+        let refill = now.duration_since(state.refilled).as_secs_f64() * rate;
+
+        state.tokens = (state.tokens + refill).min(rate) - amount as f64;
+        state.refilled = now;
+        if state.tokens < 0.0 {
+            // Lock is held while sleeping, next taker starts
+            // from the debt left by this one
+            sleep(Duration::from_secs_f64(-state.tokens / rate)).await;
+        }
+    }
+}
+
+impl Default for TokenBucket {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Throttle {
+    pub fn new(bandwidth: Bandwidth) -> Self {
+        let throttle = Self::default();
+        throttle.configure(bandwidth);
+        throttle
+    }
+
+    /// Applies new limits, waiters are not interrupted
+    pub fn configure(&self, bandwidth: Bandwidth) {
+        let rate = |limit: Option<_>| limit.map_or(0, u64::from);
+        self.upload.set_rate(rate(bandwidth.upload));
+        self.download.set_rate(rate(bandwidth.download));
+    }
+}
+
+impl Throttles {
+    /// Waits for the `bytes` to be sent to the public peer
+    pub async fn upload(&self, bytes: usize) {
+        self.proxy.upload.take(bytes).await;
+        if let Some(ref user) = self.user {
+            user.upload.take(bytes).await;
+        }
+    }
+
+    /// Waits for the `bytes` received from the public peer
+    /// to be passed to the client
+    pub async fn download(&self, bytes: usize) {
+        self.proxy.download.take(bytes).await;
+        if let Some(ref user) = self.user {
+            user.download.take(bytes).await;
+        }
+    }
+}
+
+impl RateLimits {
+    /// Throttle of the `user` with the `bandwidth` applied,
+    /// created on the first use
+    pub fn user(&self, name: &str, bandwidth: Bandwidth) -> Arc<Throttle> {
+        let throttle = match self.users.get(name) {
+            Some(throttle) => Arc::clone(&throttle),
+            None => Arc::clone(&self.users.entry(name.to_owned()).or_default()),
+        };
+        throttle.configure(bandwidth);
+
+        throttle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex as StdMutex;
+
+    use super::*;
+
+    const RATE: u64 = 1000;
+
+    async fn timed(bucket: &TokenBucket, amount: usize) -> Duration {
+        let started = Instant::now();
+        bucket.take(amount).await;
+        started.elapsed()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn one_second_worth_passes_right_away() {
+        let bucket = TokenBucket::new(RATE);
+
+        assert_eq!(timed(&bucket, 1000).await, Duration::ZERO);
+        assert_eq!(timed(&bucket, 500).await, Duration::from_millis(500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn debt_is_paid_by_the_next_taker() {
+        let bucket = TokenBucket::new(RATE);
+
+        assert_eq!(timed(&bucket, 3000).await, Duration::from_secs(2));
+        // Refill over the sleep only covered the debt
+        assert_eq!(timed(&bucket, 1000).await, Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unlimited_never_waits() {
+        let bucket = TokenBucket::default();
+
+        assert_eq!(timed(&bucket, usize::MAX).await, Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waiters_take_turns() {
+        let bucket = Arc::new(TokenBucket::new(RATE));
+        let order = Arc::new(StdMutex::new(Vec::new()));
+        bucket.take(1000).await;
+
+        let tasks: Vec<_> = ["a", "b"]
+            .into_iter()
+            .map(|name| {
+                let bucket = Arc::clone(&bucket);
+                let order = Arc::clone(&order);
+                tokio::spawn(async move {
+                    for _ in 0..3 {
+                        bucket.take(100).await;
+                        order.lock().unwrap().push(name);
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(*order.lock().unwrap(), ["a", "b", "a", "b", "a", "b"]);
+    }
+}
@@ -0,0 +1,262 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::{
+        Arc,
+        Mutex,
+        MutexGuard,
+    },
+    time::Duration,
+};
+
+use color_eyre::eyre;
+use tokio::sync::Notify;
+
+use crate::{
+    accounting::unix_now,
+    config::handle::ConfigHandle,
+    shutdown::Shutdown,
+};
+
+/// How often ports of the users that were not seen for too
+/// long are forgotten
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct Database {
+    #[serde(default)]
+    ports: BTreeMap<String, u16>,
+
+    /// Last time the users took their ports, seconds since
+    /// the epoch
+    #[serde(default)]
+    seen: BTreeMap<String, u64>,
+}
+
+#[derive(Clone, Copy)]
+struct Remembered {
+    port: u16,
+    seen: u64,
+}
+
+/// Ports assigned to the users. Reserved port is given back
+/// to its owner and refused to anyone else.
+pub struct Reservations {
+    // Static reservations are taken from the current config
+    config: ConfigHandle,
+
+    database: Option<PathBuf>,
+    remembered: Mutex<BTreeMap<String, Remembered>>,
+
+    // Wakes the writer up, see the `run`
+    changed: Notify,
+    // Held while writing, so the writes don't overtake each
+    // other
+    persisted: Mutex<Database>,
+}
+
+impl Reservations {
+    /// Reads remembered reservations from the database,
+    /// missing database is treated as empty
+    pub fn load(config: ConfigHandle) -> eyre::Result<Arc<Self>> {
+        let database = config.get().reservations.database.clone();
+        let stored = match database {
+            Some(ref path) => read_database(path)?,
+            None => Database::default(),
+        };
+
+        // Databases written before the expiry have no `seen`
+        let now = unix_now();
+        let remembered = stored
+            .ports
+            .iter()
+            .map(|(user, &port)| {
+                let seen = stored.seen.get(user).copied().unwrap_or(now);
+                (user.clone(), Remembered { port, seen })
+            })
+            .collect();
+
+        let this = Arc::new(Self {
+            config,
+            database,
+            remembered: Mutex::new(remembered),
+            changed: Notify::new(),
+            persisted: Mutex::new(stored),
+        });
+        this.expire();
+
+        Ok(this)
+    }
+
+    /// Port reserved for the `user`
+    pub fn port_of(&self, user: &str) -> Option<u16> {
+        self.config
+            .get()
+            .reservations
+            .ports
+            .get(user)
+            .copied()
+            .or_else(|| self.remembered().get(user).map(|r| r.port))
+    }
+
+    /// Whether the `port` can be taken by the `user`: it is
+    /// either not reserved or reserved for this very user
+    pub fn is_available_for(&self, port: u16, user: Option<&str>) -> bool {
+        let is_owner = |owner: &String| Some(owner.as_str()) == user;
+        let config = self.config.get();
+        let fixed = &config.reservations.ports;
+        if let Some((owner, _)) = fixed.iter().find(|(_, &p)| p == port) {
+            return is_owner(owner);
+        }
+
+        self.remembered()
+            .iter()
+            .find(|(_, r)| r.port == port)
+            .is_none_or(|(owner, _)| is_owner(owner))
+    }
+
+    /// Remembers `port` as the last port of the `user`, the
+    /// database is written by the [`Reservations::run`].
+    /// No-op if the database is not configured or
+    /// `user` has the static reservation
+    pub fn remember(&self, user: &str, port: u16) {
+        if self.database.is_none()
+            || self
+                .config
+                .get()
+                .reservations
+                .ports
+                .contains_key(user)
+        {
+            return;
+        }
+
+        self.remembered().insert(
+            user.to_owned(),
+            Remembered {
+                port,
+                seen: unix_now(),
+            },
+        );
+        self.changed.notify_one();
+    }
+
+    /// Writes changes to the database until the shutdown
+    /// and forgets ports of the users that were not
+    /// seen for `reservations.forget_after` days
+    pub async fn run(self: Arc<Self>, shutdown: Shutdown) -> eyre::Result<()> {
+        if self.database.is_none() {
+            return Ok(());
+        }
+
+        let mut expiry = tokio::time::interval(EXPIRY_INTERVAL);
+        loop {
+            tokio::select! {
+                () = shutdown.triggered() => {
+                    return Ok(());
+                }
+
+                () = self.changed.notified() => {}
+
+                _ = expiry.tick() => {
+                    self.expire();
+                }
+            }
+
+            if let Err(e) = self.flush().await {
+                tracing::error!("Failed to persist reservations: {e}");
+            }
+        }
+    }
+
+    /// Persists remembered ports. No-op if the database is
+    /// not configured or nothing changed
+    pub async fn flush(self: &Arc<Self>) -> io::Result<()> {
+        if self.database.is_none() {
+            return Ok(());
+        }
+
+        let this = Arc::clone(self);
+        tokio::task::spawn_blocking(move || this.write())
+            .await
+            .map_err(io::Error::other)?
+    }
+
+    fn expire(&self) {
+        let forget_after = self.config.get().reservations.forget_after_secs();
+        let now = unix_now();
+
+        self.remembered().retain(|user, remembered| {
+            let keep = now.saturating_sub(remembered.seen) < forget_after;
+            if !keep {
+                tracing::info!(
+                    "Port {} of the {user} was forgotten",
+                    remembered.port
+                );
+            }
+            keep
+        });
+    }
+
+    fn write(&self) -> io::Result<()> {
+        let Some(ref path) = self.database else {
+            return Ok(());
+        };
+
+        let mut persisted = self
+            .persisted
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let database = {
+            let remembered = self.remembered();
+            Database {
+                ports: remembered
+                    .iter()
+                    .map(|(user, r)| (user.clone(), r.port))
+                    .collect(),
+                seen: remembered
+                    .iter()
+                    .map(|(user, r)| (user.clone(), r.seen))
+                    .collect(),
+            }
+        };
+        if database == *persisted {
+            return Ok(());
+        }
+
+        write_database(path, &database)?;
+        *persisted = database;
+
+        Ok(())
+    }
+
+    fn remembered(&self) -> MutexGuard<'_, BTreeMap<String, Remembered>> {
+        self.remembered
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn read_database(path: &Path) -> eyre::Result<Database> {
+    match fs::read_to_string(path) {
+        Ok(contents) => toml::from_str(&contents).map_err(From::from),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Database::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Writes database to the temporary file first, so it's
+/// never left half-written
+fn write_database(path: &Path, database: &Database) -> io::Result<()> {
+    let contents = toml::to_string(database)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let temporary = path.with_extension("tmp");
+
+    fs::write(&temporary, contents)?;
+    fs::rename(temporary, path)
+}
@@ -0,0 +1,98 @@
+use std::sync::{
+    atomic::{
+        AtomicUsize,
+        Ordering,
+    },
+    Arc,
+};
+
+use tokio::sync::{
+    watch,
+    Notify,
+};
+
+struct Inner {
+    signal: watch::Sender<bool>,
+
+    active_flows: AtomicUsize,
+    drained: Notify,
+}
+
+/// Server-wide shutdown: tells tasks to stop accepting new
+/// work and tracks flows that are still active
+#[derive(Clone)]
+pub struct Shutdown {
+    inner: Arc<Inner>,
+}
+
+/// Marks flow as active until dropped
+pub struct FlowGuard {
+    inner: Arc<Inner>,
+}
+
+impl Shutdown {
+    pub fn trigger(&self) {
+        self.inner.signal.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.inner.signal.borrow()
+    }
+
+    /// Resolves once the shutdown is triggered
+    pub async fn triggered(&self) {
+        let mut rx = self.inner.signal.subscribe();
+        // Sender is owned by `self`, so channel can't be closed
+        _ = rx.wait_for(|&triggered| triggered).await;
+    }
+
+    pub fn track_flow(&self) -> FlowGuard {
+        self.inner
+            .active_flows
+            .fetch_add(1, Ordering::AcqRel);
+        FlowGuard {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+
+    pub fn active_flows(&self) -> usize {
+        self.inner.active_flows.load(Ordering::Acquire)
+    }
+
+    /// Resolves once there are no active flows
+    pub async fn drained(&self) {
+        loop {
+            let notified = self.inner.drained.notified();
+            if self.active_flows() == 0 {
+                return;
+            }
+
+            notified.await;
+        }
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                signal: watch::Sender::new(false),
+                active_flows: AtomicUsize::new(0),
+                drained: Notify::new(),
+            }),
+        }
+    }
+}
+
+impl Drop for FlowGuard {
+    fn drop(&mut self) {
+        if self
+            .inner
+            .active_flows
+            .fetch_sub(1, Ordering::AcqRel)
+            == 1
+        {
+            self.inner.drained.notify_waiters();
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use std::{
+    io,
+    net::{
+        SocketAddr,
+        TcpListener as StdTcpListener,
+    },
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Mutex,
+    },
+};
+#[cfg(unix)]
+use std::{
+    os::{
+        fd::{
+            AsFd,
+            BorrowedFd,
+            OwnedFd,
+        },
+        unix::net::UnixListener as StdUnixListener,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use tokio::net::TcpListener;
+
+#[cfg(feature = "tcpflux")]
+use crate::config::server::ListenAddress;
+
+enum Prepared {
+    Tcp(StdTcpListener),
+    #[cfg(unix)]
+    Unix(StdUnixListener),
+}
+
+/// Listener passed to the process
+#[cfg(unix)]
+struct Inherited {
+    name: String,
+    fd: OwnedFd,
+    // Passed by the service manager, which decides the
+    // address and owns the socket file
+    activated: bool,
+}
+
+/// Listening sockets of the process. They are bound before
+/// the runtime is started (and privileges are dropped), on
+/// unix they might be inherited from the previous process
+/// and are handed over to the next one on the binary
+/// upgrade.
+#[derive(Default)]
+pub struct Sockets {
+    prepared: Mutex<Vec<(String, Prepared)>>,
+
+    #[cfg(unix)]
+    inherited: Mutex<Vec<Inherited>>,
+    #[cfg(unix)]
+    bound: Mutex<Vec<(String, OwnedFd)>>,
+    // Socket files of the listeners passed by the service
+    // manager
+    #[cfg(unix)]
+    foreign_files: Mutex<Vec<PathBuf>>,
+
+    handed_over: AtomicBool,
+}
+
+impl Sockets {
+    /// Prepares listener named `name`: takes the inherited
+    /// one if it is bound on the `address`, binds the new
+    /// one otherwise. Returns the actual local address.
+    pub fn bind_tcp(
+        &self,
+        name: &str,
+        address: SocketAddr,
+    ) -> io::Result<SocketAddr> {
+        let listener = match self.take_inherited_tcp(name, address) {
+            Some(listener) => listener,
+            None => StdTcpListener::bind(address)?,
+        };
+
+        #[cfg(unix)]
+        self.register(name, listener.as_fd())?;
+
+        let local_addr = listener.local_addr()?;
+        self.prepare(name, Prepared::Tcp(listener));
+
+        Ok(local_addr)
+    }
+
+    /// Takes the listener prepared by the
+    /// [`Self::bind_tcp`], must be called within the
+    /// runtime
+    pub fn tcp_listener(&self, name: &str) -> io::Result<TcpListener> {
+        match self.take_prepared(name)? {
+            Prepared::Tcp(listener) => {
+                listener.set_nonblocking(true)?;
+                TcpListener::from_std(listener)
+            }
+            #[cfg(unix)]
+            Prepared::Unix(..) => Err(not_prepared(name)),
+        }
+    }
+
+    pub fn is_handed_over(&self) -> bool {
+        self.handed_over.load(Ordering::Acquire)
+    }
+
+    fn prepare(&self, name: &str, listener: Prepared) {
+        self.prepared
+            .lock()
+            .unwrap()
+            .push((name.to_owned(), listener));
+    }
+
+    fn take_prepared(&self, name: &str) -> io::Result<Prepared> {
+        let mut prepared = self.prepared.lock().unwrap();
+        let idx = prepared
+            .iter()
+            .position(|(n, _)| n == name)
+            .ok_or_else(|| not_prepared(name))?;
+
+        Ok(prepared.swap_remove(idx).1)
+    }
+
+    /// Prepares listener on the configured address, returns
+    /// the address in the displayable form
+    #[cfg(feature = "tcpflux")]
+    pub fn bind_listen_address(
+        &self,
+        name: &str,
+        address: &ListenAddress,
+        socket_mode: Option<u32>,
+    ) -> io::Result<String> {
+        match *address {
+            ListenAddress::Tcp(address) => self
+                .bind_tcp(name, address)
+                .map(|a| a.to_string()),
+
+            #[cfg(unix)]
+            ListenAddress::Unix(ref path) => {
+                use std::{
+                    fs,
+                    os::unix::fs::PermissionsExt,
+                };
+
+                use tcp_flux::transport::unix::bind_std;
+
+                let listener = match self.take_inherited_unix(name, path) {
+                    Some(listener) => listener,
+                    None => {
+                        let listener = bind_std(path)?;
+                        if let Some(mode) = socket_mode {
+                            fs::set_permissions(
+                                path,
+                                fs::Permissions::from_mode(mode),
+                            )?;
+                        }
+
+                        listener
+                    }
+                };
+                self.add_unix(name, listener)?;
+
+                Ok(address.to_string())
+            }
+
+            #[cfg(not(unix))]
+            ListenAddress::Unix(..) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "unix sockets are not supported on this platform",
+            )),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn take_inherited_tcp(
+        &self,
+        _name: &str,
+        _address: SocketAddr,
+    ) -> Option<StdTcpListener> {
+        None
+    }
+}
+
+#[cfg(unix)]
+impl Sockets {
+    pub fn inherit(&self, sockets: Vec<(String, OwnedFd)>) {
+        self.add_inherited(sockets, false);
+    }
+
+    /// Same as the [`Self::inherit`], but sockets are owned
+    /// by the service manager (e.g. systemd socket units).
+    /// They are used even if the configured address
+    /// differs, since the manager keeps them bound
+    /// anyway
+    pub fn inherit_activated(&self, sockets: Vec<(String, OwnedFd)>) {
+        self.add_inherited(sockets, true);
+    }
+
+    fn add_inherited(&self, sockets: Vec<(String, OwnedFd)>, activated: bool) {
+        self.inherited
+            .lock()
+            .unwrap()
+            .extend(sockets.into_iter().map(|(name, fd)| Inherited {
+                name,
+                fd,
+                activated,
+            }));
+    }
+
+    /// Whether the socket file on the `path` must be
+    /// removed once the listener is closed
+    pub fn owns_socket_file(&self, path: &Path) -> bool {
+        !self.is_handed_over()
+            && !self
+                .foreign_files
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|file| file == path)
+    }
+
+    /// Takes the inherited unix listener named `name` if it
+    /// is bound on the `path`
+    pub fn take_inherited_unix(
+        &self,
+        name: &str,
+        path: &Path,
+    ) -> Option<StdUnixListener> {
+        let inherited = self.take_inherited(name)?;
+        let listener = StdUnixListener::from(inherited.fd);
+        let matches = listener
+            .local_addr()
+            .is_ok_and(|a| a.as_pathname() == Some(path));
+
+        if inherited.activated {
+            if !matches {
+                tracing::warn!(
+                    "Activated {name} listener is not bound on {}, using it anyway",
+                    path.display()
+                );
+            }
+            self.foreign_files
+                .lock()
+                .unwrap()
+                .push(path.to_owned());
+        } else if !matches {
+            tracing::warn!(
+                "Inherited {name} listener is not bound on {}, ignoring it",
+                path.display()
+            );
+            return None;
+        }
+
+        Some(listener)
+    }
+
+    /// Prepares the unix listener named `name`
+    pub fn add_unix(&self, name: &str, listener: StdUnixListener) -> io::Result<()> {
+        self.register(name, listener.as_fd())?;
+        self.prepare(name, Prepared::Unix(listener));
+
+        Ok(())
+    }
+
+    /// Takes the listener prepared by the
+    /// [`Self::add_unix`], must be called within the
+    /// runtime
+    pub fn unix_listener(&self, name: &str) -> io::Result<tokio::net::UnixListener> {
+        match self.take_prepared(name)? {
+            Prepared::Unix(listener) => {
+                listener.set_nonblocking(true)?;
+                tokio::net::UnixListener::from_std(listener)
+            }
+            Prepared::Tcp(..) => Err(not_prepared(name)),
+        }
+    }
+
+    /// Duplicates registered listeners for the handover
+    pub fn duplicate(&self) -> io::Result<Vec<(String, OwnedFd)>> {
+        self.bound
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, fd)| Ok((name.clone(), fd.try_clone()?)))
+            .collect()
+    }
+
+    /// Marks listeners as owned by the next process, they
+    /// must not be cleaned up by this one
+    pub fn mark_handed_over(&self) {
+        self.handed_over.store(true, Ordering::Release);
+        self.bound.lock().unwrap().clear();
+    }
+
+    /// Remembers the listener to pass it to the next
+    /// process
+    fn register(&self, name: &str, fd: BorrowedFd<'_>) -> io::Result<()> {
+        let fd = fd.try_clone_to_owned()?;
+        self.bound
+            .lock()
+            .unwrap()
+            .push((name.to_owned(), fd));
+
+        Ok(())
+    }
+
+    fn take_inherited_tcp(
+        &self,
+        name: &str,
+        address: SocketAddr,
+    ) -> Option<StdTcpListener> {
+        let inherited = self.take_inherited(name)?;
+        let listener = StdTcpListener::from(inherited.fd);
+        let matches = listener.local_addr().is_ok_and(|local| {
+            local.ip() == address.ip()
+                && (address.port() == 0 || local.port() == address.port())
+        });
+
+        if !matches && inherited.activated {
+            // Binding the configured address would most likely
+            // collide with the activated socket
+            tracing::warn!(
+                "Activated {name} listener is not bound on {address}, using it \
+                 anyway"
+            );
+        } else if !matches {
+            tracing::warn!(
+                "Inherited {name} listener is not bound on {address}, ignoring it"
+            );
+            return None;
+        }
+
+        Some(listener)
+    }
+
+    fn take_inherited(&self, name: &str) -> Option<Inherited> {
+        let mut inherited = self.inherited.lock().unwrap();
+        let idx = inherited.iter().position(|i| i.name == name)?;
+
+        Some(inherited.swap_remove(idx))
+    }
+}
+
+fn not_prepared(name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{name} listener was not prepared"),
+    )
+}
+
+/// Removes the unix socket file once the listener is
+/// closed, unless it is owned by someone else
+#[cfg(unix)]
+pub struct SocketFileGuard<'a> {
+    path: &'a Path,
+    sockets: &'a Sockets,
+}
+
+#[cfg(unix)]
+impl<'a> SocketFileGuard<'a> {
+    pub const fn new(path: &'a Path, sockets: &'a Sockets) -> Self {
+        Self { path, sockets }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for SocketFileGuard<'_> {
+    fn drop(&mut self) {
+        if self.sockets.owns_socket_file(self.path) {
+            _ = std::fs::remove_file(self.path);
+        }
+    }
+}
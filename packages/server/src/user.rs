@@ -1,25 +1,34 @@
-use std::{
-    fmt::Display,
-    net::SocketAddr,
-};
+use std::fmt::Display;
 
-use flux_common::Rights;
+use flux_common::{
+    address::PeerAddress,
+    Rights,
+};
 use owo_colors::OwoColorize;
 
 #[derive(Debug)]
 pub struct User {
+    /// Set once user is authenticated
+    pub name: Option<String>,
     pub rights: Rights,
-    pub address: SocketAddr,
+    pub address: PeerAddress,
 }
 
 impl User {
-    pub fn new(rights: Rights, address: SocketAddr) -> Self {
-        Self { address, rights }
+    pub fn new(rights: Rights, address: PeerAddress) -> Self {
+        Self {
+            name: None,
+            address,
+            rights,
+        }
     }
 }
 
 impl Display for User {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.address.bold())
+        match self.name {
+            Some(ref name) => write!(f, "{}@{}", name.bold(), self.address.bold()),
+            None => write!(f, "{}", self.address.bold()),
+        }
     }
 }